@@ -28,6 +28,11 @@ use gchemol::Molecule;
 
 // [[file:../models.note::bd430804][bd430804]]
 mod cmd;
+mod retry;
+
+pub use cmd::{RunScriptError, TimeoutError};
+pub use env::ScratchPolicy;
+pub use retry::{FailureKind, RetryPolicy};
 // bd430804 ends here
 
 // [[file:../models.note::*base][base:1]]
@@ -44,9 +49,29 @@ pub struct BlackBoxModel {
     /// Set the root directory for scratch files.
     scr_dir: Option<PathBuf>,
 
+    /// How scratch directories are used among computations.
+    scratch_policy: ScratchPolicy,
+
     /// Job starting directory
+    #[allow(dead_code)]
     job_dir: Option<PathBuf>,
 
+    /// Time limit for each execution of run script.
+    timeout: Option<std::time::Duration>,
+
+    /// Grace period for processes to exit after SIGTERM before SIGKILL.
+    grace: std::time::Duration,
+
+    /// Policy for retrying failed computations.
+    retry: RetryPolicy,
+
+    /// Scratch directories of failed attempts left on disk.
+    failed_scratch_dirs: Vec<PathBuf>,
+
+    /// The last attempt failed in current scratch directory, which should be
+    /// left on disk on renewal.
+    last_attempt_failed: bool,
+
     // the field order matters
     // https://stackoverflow.com/questions/41053542/forcing-the-order-in-which-struct-fields-are-dropped
     task: Option<Task>,
@@ -60,44 +85,156 @@ pub struct BlackBoxModel {
 // base:1 ends here
 
 // [[file:../models.note::045f62c4][045f62c4]]
+/// The default grace period between SIGTERM and SIGKILL
+const TERMINATION_GRACE_PERIOD: f64 = 1.0;
+
 // NOTE: There is no implementation of Drop for std::process::Child
-/// A simple wrapper for killing child process on drop
-struct Task(std::process::Child);
+/// A simple wrapper for killing child process (and all processes in its
+/// session) on drop
+struct Task {
+    child: std::process::Child,
+    /// Grace period for processes to exit after SIGTERM before SIGKILL
+    grace: std::time::Duration,
+    /// The child process has been waited, with nothing to terminate on drop
+    reaped: bool,
+}
+
+impl Task {
+    fn new(child: std::process::Child, grace: std::time::Duration) -> Self {
+        Self {
+            child,
+            grace,
+            reaped: false,
+        }
+    }
+
+    /// Wait for the child process to exit, returning its exit status.
+    fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
+        let status = self.child.wait()?;
+        self.reaped = true;
+        Ok(status)
+    }
+
+    /// Terminate all processes in session now, returning the exit status of
+    /// the child process.
+    fn terminate(&mut self) -> std::io::Result<std::process::ExitStatus> {
+        terminate_process_group(self.child.id(), self.grace, Some(&mut self.child));
+        self.wait()
+    }
+}
 
 impl Drop for Task {
     // NOTE: There is no implementation of Drop for std::process::Child
     fn drop(&mut self) {
+        if self.reaped {
+            return;
+        }
         info!("Task dropped. Kill external commands in session.");
-        let child = &mut self.0;
-
-        if let Ok(Some(x)) = child.try_wait() {
-            info!("child process exited gracefully with status {x:?}.");
-        } else {
-            // inform child to exit gracefully
-            if let Err(e) = send_signal_term(child.id()) {
-                error!("Kill child process failure: {:?}", e);
-            }
-            std::thread::sleep(std::time::Duration::from_secs_f64(0.1));
-            // wait a few seconds for child process to exit, or the scratch
-            // directory will be removed immediately.
-            if let Ok(None) = child.try_wait() {
-                info!("Child process is still running, one second for clean up ...",);
-                std::thread::sleep(std::time::Duration::from_secs(1));
-            }
+        let pgid = self.child.id();
+        terminate_process_group(pgid, self.grace, Some(&mut self.child));
+        match self.child.wait() {
+            Ok(x) => info!("child process exited with status {x:?}."),
+            Err(e) => error!("Wait child process failure: {:?}", e),
         }
     }
 }
 
-fn send_signal_term(pid: u32) -> Result<()> {
-    use nix::sys::signal::{kill, Signal};
+// Return true if any process in group `pgid` is alive
+fn process_group_alive(pgid: nix::unistd::Pid) -> bool {
+    if nix::sys::signal::killpg(pgid, None).is_err() {
+        return false;
+    }
+    // zombies of orphaned processes are still members of the group until
+    // reaped by init, which may take a while
+    #[cfg(target_os = "linux")]
+    if let Ok(entries) = std::fs::read_dir("/proc") {
+        return entries.flatten().any(|e| {
+            let stat = std::fs::read_to_string(e.path().join("stat")).unwrap_or_default();
+            is_live_member(&stat, pgid.as_raw())
+        });
+    }
+    true
+}
+
+// Return true if the process described in /proc/<pid>/stat is in group
+// `pgid` and not a zombie. The format is "pid (comm) state ppid pgrp ...".
+#[cfg(target_os = "linux")]
+fn is_live_member(stat: &str, pgid: i32) -> bool {
+    let Some((_, rest)) = stat.rsplit_once(')') else {
+        return false;
+    };
+    let fields: Vec<_> = rest.split_whitespace().take(3).collect();
+    fields.len() == 3 && fields[0] != "Z" && fields[2].parse() == Ok(pgid)
+}
 
-    let pid = nix::unistd::Pid::from_raw(pid as i32);
-    let signal = Signal::SIGTERM;
-    info!("Inform child process {} to exit by sending signal {:?}.", pid, signal);
-    kill(pid, signal).with_context(|| format!("kill process {:?}", pid))?;
+/// Terminate all processes in process group `pgid`: inform them to exit
+/// gracefully with SIGTERM, and kill them with SIGKILL if still running
+/// after `grace` period. The group leader `child` if available will be
+/// reaped when exited. Return false if no such process group.
+fn terminate_process_group(
+    pgid: u32,
+    grace: std::time::Duration,
+    mut child: Option<&mut std::process::Child>,
+) -> bool {
+    use nix::sys::signal::{killpg, Signal};
+
+    let pgid = nix::unistd::Pid::from_raw(pgid as i32);
+    info!("Inform processes in group {} to exit by sending signal SIGTERM.", pgid);
+    if let Err(e) = killpg(pgid, Signal::SIGTERM) {
+        debug!("signal process group {pgid} failure: {e:?}");
+        return false;
+    }
 
-    Ok(())
+    // wait for processes to exit, or the scratch directory will be removed
+    // immediately.
+    if wait_process_group(pgid, grace, child.as_deref_mut()) {
+        return true;
+    }
+
+    warn!("Processes in group {pgid} are still running after {grace:?}. Kill them with SIGKILL.");
+    if let Err(e) = killpg(pgid, Signal::SIGKILL) {
+        error!("Kill process group {pgid} failure: {e:?}");
+    }
+    // killed processes disappear only after being reaped
+    if !wait_process_group(pgid, std::time::Duration::from_secs(1), child) {
+        warn!("Processes in group {pgid} are still alive after SIGKILL.");
+    }
+    true
+}
+
+// Wait at most `timeout` for all processes in group `pgid` to exit, reaping
+// the group leader `child` if available. Return true if the group is gone.
+fn wait_process_group(
+    pgid: nix::unistd::Pid,
+    timeout: std::time::Duration,
+    mut child: Option<&mut std::process::Child>,
+) -> bool {
+    let now = std::time::Instant::now();
+    loop {
+        // a zombie group leader is still a member of the group
+        if let Some(child) = child.as_mut() {
+            let _ = child.try_wait();
+        }
+        if !process_group_alive(pgid) {
+            return true;
+        }
+        if now.elapsed() >= timeout {
+            return false;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
 }
+
+// Parse time duration in seconds for env var `key`, such as "3600" or "0.5"
+fn parse_seconds(key: &str, s: Option<&str>) -> Result<Option<std::time::Duration>> {
+    let Some(s) = s else {
+        return Ok(None);
+    };
+    let secs: f64 = s.trim().parse().with_context(|| format!("invalid {key}: {s:?}"))?;
+    ensure!(secs >= 0.0 && secs.is_finite(), "invalid {key}: {s:?}");
+    Ok(Some(std::time::Duration::from_secs_f64(secs)))
+}
+
 // 045f62c4 ends here
 
 // [[file:../models.note::6cc8ead1][6cc8ead1]]
@@ -119,7 +256,82 @@ mod env {
         )
     }
 
+    /// Policy for using scratch directories among computations
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub enum ScratchPolicy {
+        /// Reuse the same scratch directory for all computations, which is
+        /// useful for restarting from files of the previous computation.
+        #[default]
+        Reuse,
+        /// Use a fresh scratch directory for each computation, removing the
+        /// previous one unless it failed under retry policy.
+        FreshPerCall,
+        /// The same as `FreshPerCall`, but copy selected files (such as
+        /// restart files) from the previous scratch directory if exist.
+        FreshPerCallCopy(Vec<String>),
+    }
+
+    impl ScratchPolicy {
+        /// Read policy from env vars in .env file:
+        ///
+        /// - `BBM_SCRATCH_POLICY`: reuse, fresh, or fresh-copy
+        /// - `BBM_SCRATCH_COPY_FILES`: comma separated files to be copied
+        ///   for fresh-copy policy
+        fn from_envfile(envfile: &envfile::EnvFile) -> Result<Self> {
+            let policy = match envfile.get("BBM_SCRATCH_POLICY").map(|s| s.trim()) {
+                None | Some("reuse") => Self::Reuse,
+                Some("fresh") => Self::FreshPerCall,
+                Some("fresh-copy") => {
+                    let files = envfile.get("BBM_SCRATCH_COPY_FILES").unwrap_or_default();
+                    let files = files.split(',').map(|f| f.trim()).filter(|f| !f.is_empty());
+                    let files = files.map(|f| f.to_owned()).collect_vec();
+                    ensure!(!files.is_empty(), "no BBM_SCRATCH_COPY_FILES for fresh-copy scratch policy");
+                    Self::FreshPerCallCopy(files)
+                }
+                Some(s) => bail!("invalid BBM_SCRATCH_POLICY: {s:?}"),
+            };
+            Ok(policy)
+        }
+    }
+
     impl BlackBoxModel {
+        /// Switch to a fresh scratch directory for a new computation if
+        /// required by scratch policy.
+        pub(super) fn renew_scratch_directory(&mut self) -> Result<()> {
+            let failed = std::mem::take(&mut self.last_attempt_failed);
+            let files = match &self.scratch_policy {
+                ScratchPolicy::Reuse => return Ok(()),
+                ScratchPolicy::FreshPerCall => vec![],
+                ScratchPolicy::FreshPerCallCopy(files) => files.clone(),
+            };
+            // leave the directory of failed attempt on disk for inspection
+            let (old, _tdir) = if failed {
+                (self.detach_scratch_directory(), None)
+            } else {
+                let tdir = self.temp_dir.take();
+                (tdir.as_ref().map(|d| d.path().to_owned()), tdir)
+            };
+            // nothing to do for the first computation
+            let Some(old) = old else {
+                return Ok(());
+            };
+            // the interactive process is running in the old directory
+            self.task = None;
+
+            self.prepare_compute_env()?;
+            let new = self.temp_dir.as_ref().expect("scratch dir").path();
+            for f in &files {
+                let src = old.join(f);
+                if src.exists() {
+                    let dst = new.join(f);
+                    std::fs::copy(&src, &dst).with_context(|| format!("copy {src:?} to {dst:?}"))?;
+                } else {
+                    debug!("file to copy not found in scratch directory: {f:?}");
+                }
+            }
+            Ok(())
+        }
+
         /// Create a temporary working directory and prepare running script
         pub(super) fn prepare_compute_env(&mut self) -> Result<PathBuf> {
             let run = "run";
@@ -159,12 +371,21 @@ mod env {
             let run_file = envfile.get("BBM_RUN_FILE").unwrap_or("submit.sh");
             let tpl_file = envfile.get("BBM_TPL_FILE").unwrap_or("input.hbs");
             let int_file_opt = envfile.get("BBM_INT_FILE");
+            let timeout = parse_seconds("BBM_TIMEOUT", envfile.get("BBM_TIMEOUT"))?;
+            ensure!(timeout.is_none_or(|t| !t.is_zero()), "invalid BBM_TIMEOUT: zero");
             let bbm = BlackBoxModel {
                 run_file: dir.join(run_file),
                 tpl_file: dir.join(tpl_file),
                 int_file: int_file_opt.map(|f| dir.join(f)),
                 scr_dir: envfile.get("BBM_SCR_DIR").map(|x| x.into()),
+                scratch_policy: ScratchPolicy::from_envfile(&envfile)?,
                 job_dir: std::env::current_dir()?.into(),
+                timeout,
+                grace: parse_seconds("BBM_TERM_GRACE", envfile.get("BBM_TERM_GRACE"))?
+                    .unwrap_or_else(|| std::time::Duration::from_secs_f64(TERMINATION_GRACE_PERIOD)),
+                retry: RetryPolicy::from_envfile(&envfile)?,
+                failed_scratch_dirs: vec![],
+                last_attempt_failed: false,
                 temp_dir: None,
                 task: None,
                 ncalls: 0,
@@ -193,37 +414,124 @@ mod env {
 }
 // 6cc8ead1 ends here
 
+// [[file:../models.note::7b2d9e4c][7b2d9e4c]]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Return the host name of this machine
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    let name = nix::unistd::gethostname(&mut buf).ok()?;
+    Some(name.to_string_lossy().into_owned())
+}
+
+// Provenance of a call of the external program
+struct Provenance {
+    host: Option<String>,
+    start_time: SystemTime,
+    scratch_dir: Option<PathBuf>,
+    ncalls: usize,
+}
+
+impl Provenance {
+    // Record metadata in `mp`: host, start time (seconds since Unix epoch),
+    // wall time (seconds), scratch directory and the number of calls.
+    fn record(&self, mp: &mut Computed) {
+        if let Some(host) = &self.host {
+            mp.set_meta("host", host);
+        }
+        if let Ok(t) = self.start_time.duration_since(UNIX_EPOCH) {
+            mp.set_meta("start_time", format!("{:.3}", t.as_secs_f64()));
+        }
+        if let Ok(t) = self.start_time.elapsed() {
+            mp.set_meta("wall_time", format!("{:.3}", t.as_secs_f64()));
+        }
+        if let Some(d) = &self.scratch_dir {
+            mp.set_meta("scratch_dir", d.display().to_string());
+        }
+        mp.set_meta("ncalls", self.ncalls.to_string());
+    }
+}
+
+impl BlackBoxModel {
+    fn provenance(&self, start_time: SystemTime) -> Provenance {
+        Provenance {
+            host: hostname(),
+            start_time,
+            scratch_dir: self.temp_dir.as_ref().map(|d| d.path().to_owned()),
+            ncalls: self.ncalls,
+        }
+    }
+}
+// 7b2d9e4c ends here
+
 // [[file:../models.note::360435b0][360435b0]]
 impl BlackBoxModel {
     fn compute_normal(&mut self, mol: &Molecule) -> Result<Computed> {
+        let start_time = SystemTime::now();
         // 1. render input text with the template
-        let txt = self.render_input(&mol)?;
+        let txt = self.render_input(mol)?;
 
         // 2. call external engine
+        self.renew_scratch_directory()?;
         let output = self.submit_cmd(&txt)?;
 
         // 3. collect model properties
-        let mp = output
+        let mut mp = output
             .parse()
             .with_context(|| format!("failed to parse computed results: {:?}", output))?;
 
         self.ncalls += 1;
+        self.provenance(start_time).record(&mut mp);
         Ok(mp)
     }
 
     fn compute_normal_bunch(&mut self, mols: &[Molecule]) -> Result<Vec<Computed>> {
+        let start_time = SystemTime::now();
         // 1. render input text with the template
         let txt = self.render_input_bunch(mols)?;
 
         // 2. call external engine
+        self.renew_scratch_directory()?;
         let output = self.submit_cmd(&txt)?;
 
         // 3. collect model properties
-        let all = Computed::parse_all(&output)?;
+        let mut all = Computed::parse_all(&output)?;
 
         self.ncalls += 1;
+        let provenance = self.provenance(start_time);
+        all.iter_mut().for_each(|mp| provenance.record(mp));
         Ok(all)
     }
+
+    fn compute_normal_bunch_streaming<F>(&mut self, mols: &[Molecule], mut f: F) -> Result<()>
+    where
+        F: FnMut(usize, Computed) -> Result<()>,
+    {
+        let start_time = SystemTime::now();
+        // 1. render input text with the template
+        let txt = self.render_input_bunch(mols)?;
+
+        // 2. call external engine, and collect model properties as soon as
+        // they are available
+        self.renew_scratch_directory()?;
+        self.prepare_compute_env()?;
+        let mut provenance = self.provenance(start_time);
+        provenance.ncalls += 1;
+        let mut i = 0;
+        let n = self.submit_cmd_streaming(&txt, |mut mp| {
+            ensure!(i < mols.len(), "found more results than {} molecules", mols.len());
+            mp.validate_against(&mols[i])
+                .with_context(|| format!("invalid results of molecule {i}"))?;
+            provenance.record(&mut mp);
+            f(i, mp)?;
+            i += 1;
+            Ok(())
+        })?;
+        ensure!(n == mols.len(), "expect results of {} molecules, but found {n}", mols.len());
+
+        self.ncalls += 1;
+        Ok(())
+    }
 }
 // 360435b0 ends here
 
@@ -249,7 +557,7 @@ impl BlackBoxModel {
     pub fn render_input_bunch(&self, mols: &[Molecule]) -> Result<String> {
         let mut txt = String::new();
         for mol in mols.iter() {
-            let part = self.render_input(&mol)?;
+            let part = self.render_input(mol)?;
             txt.push_str(&part);
         }
 
@@ -268,13 +576,59 @@ impl BlackBoxModel {
     /// keep scratch files for user inspection of failure.
     pub fn keep_scratch_files(self) {
         if let Some(tdir) = self.temp_dir {
-            let path = tdir.into_path();
+            let path = tdir.keep();
             println!("Directory for scratch files: {}", path.display());
         } else {
             warn!("No temp dir found.");
         }
     }
 
+    /// Compute a bunch of molecules in one call, handing over the result of
+    /// each molecule (with its index in `mols`) to `f` as soon as it is
+    /// available, while the external program is still running. SCF
+    /// convergence is left to be checked by `f`.
+    pub fn compute_bunch_streaming<F>(&mut self, mols: &[Molecule], f: F) -> Result<()>
+    where
+        F: FnMut(usize, Computed) -> Result<()>,
+    {
+        self.compute_normal_bunch_streaming(mols, f)
+    }
+
+    /// Set time limit for each execution of run script, overriding
+    /// `BBM_TIMEOUT` in .env file. All processes started by the run script
+    /// will be terminated when time is up, and `TimeoutError` returned.
+    pub fn set_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Set grace period for processes started by run script to exit after
+    /// SIGTERM before being killed by SIGKILL, overriding `BBM_TERM_GRACE`
+    /// in .env file.
+    pub fn set_termination_grace_period(&mut self, grace: std::time::Duration) {
+        self.grace = grace;
+    }
+
+    /// Set policy for using scratch directories among computations,
+    /// overriding `BBM_SCRATCH_POLICY` in .env file. The interactive process
+    /// if any will be restarted in each fresh scratch directory.
+    pub fn set_scratch_policy(&mut self, policy: ScratchPolicy) {
+        self.scratch_policy = policy;
+    }
+
+    /// Set policy for retrying failed computations in `compute` and
+    /// `compute_bunch`, overriding `BBM_RETRY_*` in .env file. Streaming
+    /// computations will not be retried.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    /// Return scratch directories of failed attempts left on disk for
+    /// inspection, when retrying in a fresh scratch directory as required by
+    /// retry policy or scratch policy.
+    pub fn failed_scratch_dirs(&self) -> &[PathBuf] {
+        &self.failed_scratch_dirs
+    }
+
     /// Return the number of potentail evaluations
     pub fn number_of_evaluations(&self) -> usize {
         self.ncalls
@@ -282,31 +636,75 @@ impl BlackBoxModel {
 }
 // pub/methods:1 ends here
 
+// [[file:../models.note::1c6e2f8a][1c6e2f8a]]
+/// Error for results explicitly reported with SCF not converged. This is
+/// recoverable: the results are kept for inspection or to be accepted anyway.
+#[derive(Debug, Clone)]
+pub struct ScfNotConverged {
+    /// The indices of molecules with SCF not converged
+    pub indices: Vec<usize>,
+    /// All computed results, in the same order as input molecules
+    pub computed: Vec<Computed>,
+}
+
+impl std::fmt::Display for ScfNotConverged {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SCF not converged for molecules {:?}", self.indices)
+    }
+}
+
+impl std::error::Error for ScfNotConverged {}
+
+// Return error if SCF not converged for any of `computed`.
+fn check_scf_convergence(computed: Vec<Computed>) -> Result<Vec<Computed>> {
+    let indices = computed
+        .iter()
+        .positions(|mp| mp.get_scf_converged() == Some(false))
+        .collect_vec();
+    if indices.is_empty() {
+        Ok(computed)
+    } else {
+        Err(ScfNotConverged { indices, computed }.into())
+    }
+}
+// 1c6e2f8a ends here
+
 // [[file:../models.note::5ff4e3f1][5ff4e3f1]]
-impl ChemicalModel for BlackBoxModel {
-    fn compute(&mut self, mol: &Molecule) -> Result<Computed> {
+impl BlackBoxModel {
+    fn compute_once(&mut self, mol: &Molecule) -> Result<Computed> {
         let mp = self.compute_normal(mol)?;
 
-        // sanity checking: the associated structure should have the same number
-        // of atoms
-        debug_assert!({
-            let n = mol.natoms();
-            if let Some(pmol) = mp.get_molecule() {
-                pmol.natoms() == n
-            } else {
-                true
-            }
-        });
+        // sanity checking: the results should be consistent with input
+        // structure
+        mp.validate_against(mol)
+            .with_context(|| format!("invalid results from {:?}", self.run_file))?;
+
+        // SCF not converged is a recoverable error
+        let mp = check_scf_convergence(vec![mp])?.remove(0);
 
         Ok(mp)
     }
 
-    fn compute_bunch(&mut self, mols: &[Molecule]) -> Result<Vec<Computed>> {
+    fn compute_bunch_once(&mut self, mols: &[Molecule]) -> Result<Vec<Computed>> {
         let all = self.compute_normal_bunch(mols)?;
 
         // one-to-one mapping
-        debug_assert_eq!(mols.len(), all.len());
-        Ok(all)
+        ensure!(mols.len() == all.len(), "expect results of {} molecules, but found {}", mols.len(), all.len());
+        for (i, (mol, mp)) in mols.iter().zip(&all).enumerate() {
+            mp.validate_against(mol)
+                .with_context(|| format!("invalid results of molecule {i} from {:?}", self.run_file))?;
+        }
+        check_scf_convergence(all)
+    }
+}
+
+impl ChemicalModel for BlackBoxModel {
+    fn compute(&mut self, mol: &Molecule) -> Result<Computed> {
+        self.with_retry(|bbm| bbm.compute_once(mol))
+    }
+
+    fn compute_bunch(&mut self, mols: &[Molecule]) -> Result<Vec<Computed>> {
+        self.with_retry(|bbm| bbm.compute_bunch_once(mols))
     }
 }
 // 5ff4e3f1 ends here
@@ -327,4 +725,198 @@ fn test_bbm() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_bbm_scf_not_converged() -> Result<()> {
+    let mut bbm = BlackBoxModel::from_dir("./tests/files/dummy-scf")?;
+    let mol = Molecule::from_file("./tests/files/si5.xyz")?;
+    let e = bbm.compute(&mol).unwrap_err();
+    let e = e.downcast_ref::<ScfNotConverged>().expect("SCF not converged");
+    assert_eq!(e.indices, [0]);
+    assert_eq!(e.computed[0].get_scf_iterations(), Some(100));
+    assert_eq!(e.computed[0].get_energy(), Some(-5.0));
+    assert_eq!(e.computed[0].get_meta("ncalls"), Some("1"));
+    assert!(e.computed[0].get_meta("wall_time").is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_bbm_script_failure() -> Result<()> {
+    let mut bbm = BlackBoxModel::from_dir("./tests/files/dummy-fail")?;
+    let mol = Molecule::from_file("./tests/files/si5.xyz")?;
+    let e = bbm.compute(&mol).unwrap_err();
+    let e = e.downcast_ref::<RunScriptError>().expect("run script error");
+    assert_eq!(e.status.code(), Some(3));
+    assert!(e.stderr_tail.ends_with("SCF crashed"));
+    assert!(!e.stderr_tail.contains("line 1\n"));
+    assert!(e.scratch_dir.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_bbm_timeout() -> Result<()> {
+    let mut bbm = BlackBoxModel::from_dir("./tests/files/dummy-hang")?;
+    let mol = Molecule::from_file("./tests/files/si5.xyz")?;
+    let now = std::time::Instant::now();
+    let e = bbm.compute(&mol).unwrap_err();
+    assert!(e.downcast_ref::<TimeoutError>().is_some());
+    // the grandchild holding stdout should also be killed
+    assert!(now.elapsed().as_secs_f64() < 10.0);
+
+    // still usable for the next call
+    let mp = bbm.compute(&mol)?;
+    assert_eq!(mp.get_energy(), Some(-5.0));
+    assert_eq!(bbm.number_of_evaluations(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_bbm_retry() -> Result<()> {
+    let mol = Molecule::from_file("./tests/files/si5.xyz")?;
+    let policy = RetryPolicy {
+        max_attempts: 2,
+        backoff: std::time::Duration::ZERO,
+        ..Default::default()
+    };
+
+    // succeed on retry in the same scratch directory
+    let mut bbm = BlackBoxModel::from_dir("./tests/files/dummy-flaky")?;
+    assert!(bbm.compute(&mol).is_err());
+    bbm.set_retry_policy(policy.clone());
+    let mp = bbm.compute(&mol)?;
+    assert_eq!(mp.get_energy(), Some(-5.0));
+    assert!(bbm.failed_scratch_dirs().is_empty());
+
+    // give up, leaving scratch directories of all attempts on disk
+    let mut bbm = BlackBoxModel::from_dir("./tests/files/dummy-fail")?;
+    bbm.set_retry_policy(RetryPolicy { fresh_scratch: true, ..policy.clone() });
+    let e = bbm.compute(&mol).unwrap_err();
+    assert_eq!(FailureKind::of(&e), Some(FailureKind::RunScript));
+    let dirs = bbm.failed_scratch_dirs().to_vec();
+    assert_eq!(dirs.len(), 2);
+    assert_ne!(dirs[0], dirs[1]);
+    for d in dirs {
+        assert!(d.exists());
+        std::fs::remove_dir_all(d)?;
+    }
+
+    // a fresh scratch directory for each attempt required by scratch policy
+    let mut bbm = BlackBoxModel::from_dir("./tests/files/dummy-fail")?;
+    bbm.set_scratch_policy(ScratchPolicy::FreshPerCall);
+    bbm.set_retry_policy(RetryPolicy { max_attempts: 3, ..policy });
+    let e = bbm.compute(&mol).unwrap_err();
+    let last = e.downcast_ref::<RunScriptError>().expect("run script error").scratch_dir.clone();
+    assert_eq!(bbm.failed_scratch_dirs().len(), 2);
+    // the last one is left on disk on the next call
+    assert!(bbm.compute(&mol).is_err());
+    let dirs = bbm.failed_scratch_dirs().to_vec();
+    assert_eq!(dirs.len(), 5);
+    assert_eq!(dirs[2], last);
+    assert_eq!(dirs.iter().unique().count(), 5);
+    for d in dirs {
+        assert!(d.exists());
+        std::fs::remove_dir_all(d)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_bbm_scratch_policy() -> Result<()> {
+    let mol = Molecule::from_file("./tests/files/si5.xyz")?;
+    let mut bbm = BlackBoxModel::from_dir("./tests/files/dummy-sp")?;
+    let scratch_dir = |mp: &Computed| PathBuf::from(mp.get_meta("scratch_dir").unwrap());
+
+    let d1 = scratch_dir(&bbm.compute(&mol)?);
+    let d2 = scratch_dir(&bbm.compute(&mol)?);
+    assert_eq!(d1, d2);
+
+    // leave a restart file in the scratch directory
+    gut::fs::write_to_file(d2.join("restart"), "restart")?;
+    bbm.set_scratch_policy(ScratchPolicy::FreshPerCallCopy(vec!["restart".into()]));
+    let d3 = scratch_dir(&bbm.compute(&mol)?);
+    assert_ne!(d2, d3);
+    assert!(!d2.exists());
+    assert!(d3.join("restart").exists());
+
+    bbm.set_scratch_policy(ScratchPolicy::FreshPerCall);
+    let d4 = scratch_dir(&bbm.compute(&mol)?);
+    assert_ne!(d3, d4);
+    assert!(!d4.join("restart").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_bbm_task_drop() -> Result<()> {
+    // a script hanging with a grandchild process
+    let tdir = tempfile::tempdir()?;
+    let cmd = cmd::Cmd {
+        env_vars: Default::default(),
+        wrk_dir: tdir.path().to_owned(),
+        cmd: "./tests/files/dummy-hang/submit.sh".parse()?,
+        input: String::new(),
+        timeout: None,
+        grace: std::time::Duration::from_secs_f64(0.2),
+    };
+    let cmd = cmd::Cmd { cmd: cmd.cmd.canonicalize()?, ..cmd };
+    let child = cmd.create_child_process()?;
+    let pgid = nix::unistd::Pid::from_raw(child.id() as i32);
+    // in a new session
+    assert_eq!(nix::unistd::getsid(Some(pgid))?, pgid);
+
+    std::thread::sleep(std::time::Duration::from_secs_f64(0.2));
+    assert!(process_group_alive(pgid));
+    drop(Task::new(child, cmd.grace));
+    assert!(!process_group_alive(pgid));
+
+    Ok(())
+}
+
+#[test]
+fn test_bbm_streaming() -> Result<()> {
+    let mut bbm = BlackBoxModel::from_dir("./tests/files/dummy-sp")?;
+    let mol5 = Molecule::from_file("./tests/files/si5.xyz")?;
+    let mol10 = Molecule::from_file("./tests/files/si10.xyz")?;
+    let mols = vec![mol5, mol10];
+
+    let mut energies = vec![];
+    bbm.compute_bunch_streaming(&mols, |i, mp| {
+        assert_eq!(mp.get_forces().unwrap().len(), mols[i].natoms());
+        energies.push(mp.get_energy().unwrap());
+        Ok(())
+    })?;
+    assert_eq!(energies, [-5.0, -10.0]);
+    assert_eq!(bbm.number_of_evaluations(), 1);
+
+    // the same as normal bunch mode
+    let all = bbm.compute_bunch(&mols)?;
+    assert_eq!(all[1].get_energy(), Some(-10.0));
+
+    // all processes of the script are terminated when `f` fails
+    let mut bbm = BlackBoxModel::from_dir("./tests/files/dummy-stream-hang")?;
+    let mut scratch_dir = None;
+    let now = std::time::Instant::now();
+    let e = bbm.compute_bunch_streaming(&mols[..1], |_, mp| {
+        scratch_dir = mp.get_meta("scratch_dir").map(PathBuf::from);
+        bail!("stop here");
+    });
+    assert!(e.is_err());
+    assert!(now.elapsed().as_secs_f64() < 10.0);
+    let pid = gut::fs::read_file(scratch_dir.expect("scratch dir").join("pid"))?;
+    let pgid = nix::unistd::Pid::from_raw(pid.trim().parse()?);
+    assert!(!process_group_alive(pgid));
+
+    // exit status of the script wins over incomplete output
+    let mut bbm = BlackBoxModel::from_dir("./tests/files/dummy-crash")?;
+    let e = bbm.compute_bunch_streaming(&mols[..1], |_, _| Ok(())).unwrap_err();
+    let e = e.downcast_ref::<RunScriptError>().expect("run script error");
+    assert_eq!(e.status.code(), Some(3));
+    assert_eq!(e.stderr_tail, "SCF crashed");
+
+    Ok(())
+}
 // ba896ae9 ends here
@@ -5,6 +5,7 @@ use std::process::{Child, Command, Stdio};
 
 // [[file:../../models.note::6e72cbab][6e72cbab]]
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// Represents the command for local or remote execution
 pub struct Cmd {
@@ -16,11 +17,175 @@ pub struct Cmd {
     pub cmd: PathBuf,
     /// stream for stdin
     pub input: String,
+    /// Time limit for execution
+    pub timeout: Option<Duration>,
+    /// Grace period for processes to exit after SIGTERM before SIGKILL
+    pub grace: Duration,
 }
 // 6e72cbab ends here
 
+// [[file:../../models.note::a9d3e1f6][a9d3e1f6]]
+use std::process::ExitStatus;
+
+/// The number of lines kept in the tail of stderr
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Error for run script exited with non-zero status
+#[derive(Debug, Clone)]
+pub struct RunScriptError {
+    /// The script executed
+    pub script: PathBuf,
+    /// The exit status of the script
+    pub status: ExitStatus,
+    /// The last lines of stderr output
+    pub stderr_tail: String,
+    /// The scratch directory where the script was executed, which will be
+    /// removed on drop of BlackBoxModel unless `keep_scratch_files` called.
+    pub scratch_dir: PathBuf,
+}
+
+impl std::fmt::Display for RunScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "run script {:?} failed with {}", self.script, self.status)?;
+        writeln!(f, "scratch directory: {:?}", self.scratch_dir)?;
+        write!(f, "stderr:\n{}", self.stderr_tail)
+    }
+}
+
+impl std::error::Error for RunScriptError {}
+
+// Forward stderr of `child` to our stderr in background to avoid blocking,
+// keeping only the last lines for error report.
+fn read_stderr_in_background(child: &mut Child) -> Option<std::thread::JoinHandle<String>> {
+    use std::collections::VecDeque;
+    use std::io::BufRead;
+
+    let stderr = child.stderr.take()?;
+    let h = std::thread::spawn(move || {
+        let mut tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+        let mut reader = std::io::BufReader::new(stderr);
+        let mut buf = vec![];
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => break,
+                Ok(_) => {
+                    let _ = std::io::stderr().write_all(&buf);
+                    if tail.len() == STDERR_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(String::from_utf8_lossy(&buf).trim_end().to_owned());
+                }
+                Err(e) => {
+                    error!("Failed to read stderr: {e:?}");
+                    break;
+                }
+            }
+        }
+        tail.into_iter().join("\n")
+    });
+    Some(h)
+}
+
+// Return true if the process exited with `status` on signals sent in
+// `terminate_process_group`.
+fn terminated_by_us(status: ExitStatus) -> bool {
+    use nix::sys::signal::Signal;
+    use std::os::unix::process::ExitStatusExt;
+
+    status.signal().is_some_and(|s| s == Signal::SIGTERM as i32 || s == Signal::SIGKILL as i32)
+}
+
+/// Error for run script killed on timeout
+#[derive(Debug, Clone)]
+pub struct TimeoutError {
+    /// The script executed
+    pub script: PathBuf,
+    /// The time limit
+    pub timeout: Duration,
+    /// The scratch directory where the script was executed
+    pub scratch_dir: PathBuf,
+}
+
+impl std::fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "run script {:?} killed after timeout of {:?} (scratch directory: {:?})",
+            self.script, self.timeout, self.scratch_dir
+        )
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+// Watch a child process in a background thread, terminating its process
+// group when time is up.
+struct Watchdog {
+    cancel: std::sync::mpsc::Sender<()>,
+    handle: std::thread::JoinHandle<bool>,
+}
+
+impl Watchdog {
+    fn start(child: &Child, timeout: Duration, grace: Duration) -> Self {
+        use std::sync::mpsc::{channel, RecvTimeoutError};
+
+        let pgid = child.id();
+        let (cancel, rx) = channel();
+        let handle = std::thread::spawn(move || match rx.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Timeout) => {
+                warn!("Time is up ({timeout:?}). Terminate processes in group {pgid}.");
+                terminate_process_group(pgid, grace, None)
+            }
+            _ => false,
+        });
+        Self { cancel, handle }
+    }
+
+    // Stop watching, returning true if time was up.
+    fn stop(self) -> bool {
+        let _ = self.cancel.send(());
+        self.handle.join().unwrap_or(false)
+    }
+}
+// a9d3e1f6 ends here
+
 // [[file:../../models.note::6d640b53][6d640b53]]
 impl Cmd {
+    // Start a watchdog for `child` if time limit set
+    fn watch(&self, child: &Child) -> Option<Watchdog> {
+        self.timeout.map(|t| Watchdog::start(child, t, self.grace))
+    }
+
+    // Return `TimeoutError` if time was up.
+    fn check_timeout(&self, watchdog: Option<Watchdog>) -> Result<()> {
+        match (watchdog.map(|w| w.stop()), self.timeout) {
+            (Some(true), Some(timeout)) => {
+                let e = TimeoutError {
+                    script: self.cmd.clone(),
+                    timeout,
+                    scratch_dir: self.wrk_dir.clone(),
+                };
+                Err(e.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Return error if `status` indicates failure, with the tail of stderr
+    fn check_status(&self, status: ExitStatus, stderr_tail: String) -> Result<()> {
+        if status.success() {
+            return Ok(());
+        }
+        let e = RunScriptError {
+            script: self.cmd.clone(),
+            status,
+            stderr_tail,
+            scratch_dir: self.wrk_dir.clone(),
+        };
+        Err(e.into())
+    }
+
     // create Command for run `script`
     fn create_command(&self, script: &Path) -> std::process::Command {
         debug!("run script: {:?}", script);
@@ -29,27 +194,58 @@ impl Cmd {
             trace!("env {k:?} = {v:?}");
         }
         command.current_dir(&self.wrk_dir).envs(&self.env_vars);
+        // in a new session (and process group), so that all processes started
+        // by the script could be terminated together
+        //
+        // SAFETY: the closure runs in the forked child before exec, where only
+        // async-signal-safe operations are allowed. setsid is
+        // async-signal-safe, and the closure does not allocate: the error is
+        // built from errno.
+        unsafe {
+            std::os::unix::process::CommandExt::pre_exec(&mut command, || {
+                nix::unistd::setsid().map_err(|_| std::io::Error::last_os_error())?;
+                Ok(())
+            });
+        }
         command
     }
 
-    // Run cmd with `input` as stdin, and returns output on success.
+    // Run cmd with `input` as stdin, and returns output on success. Return
+    // `RunScriptError` if exited with non-zero status, or `TimeoutError` if
+    // killed on timeout.
     pub fn run_with_input(&self) -> Result<String> {
+        let mut child = self.spawn_with_input()?;
+        let watchdog = self.watch(&child);
+        let stderr = read_stderr_in_background(&mut child);
+        let output = child.wait_with_output().context("Failed to read stdout")?;
+        self.check_timeout(watchdog)?;
+        let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+        self.check_status(output.status, stderr)?;
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    // Spawn cmd with `input` written to stdin in background, and stdout
+    // piped for reading while the process is still running. Stderr is also
+    // piped, which should be forwarded in background by caller.
+    pub fn spawn_with_input(&self) -> Result<Child> {
         let mut child = self
             .create_command(&self.cmd)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()
             .with_context(|| format!("Failed to run script: {:?}", &self.cmd))?;
 
-        child
-            .stdin
-            .as_mut()
-            .context("Failed to open stdin")?
-            .write_all(self.input.as_bytes())
-            .context("Failed to write to stdin")?;
+        // write stdin in another thread to avoid blocking on large output
+        let mut stdin = child.stdin.take().context("Failed to open stdin")?;
+        let input = self.input.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = stdin.write_all(input.as_bytes()) {
+                error!("Failed to write to stdin: {e:?}");
+            }
+        });
 
-        let output = child.wait_with_output().context("Failed to read stdout")?;
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        Ok(child)
     }
 
     // create child process
@@ -65,6 +261,7 @@ impl Cmd {
 // 6d640b53 ends here
 
 // [[file:../../models.note::8f5db6e5][8f5db6e5]]
+#[allow(dead_code)]
 impl Cmd {
     /// Return bash script.
     pub fn bash_script(&self) -> String {
@@ -105,12 +302,11 @@ impl BlackBoxModel {
     /// Return bash script ready for execution. Interactive run is not
     /// handled here.
     pub fn bash_script_for_execution(&mut self, mol: &Molecule) -> Result<String> {
-        let txt = self.render_input(&mol)?;
+        let txt = self.render_input(mol)?;
         // do not use temp dir for remote execution
         let mut cmd = self.create_onetime_cmd(&txt)?;
         cmd.cmd = self.run_file.to_owned();
         cmd.wrk_dir = self.run_file.parent().unwrap().to_owned();
-        let input = self.render_input(mol)?;
         Ok(cmd.bash_script())
     }
 }
@@ -143,7 +339,8 @@ impl BlackBoxModel {
 
         let env_vars = env_vars.into_iter().collect();
         let cmd = run_file.to_owned();
-        let cmd = Cmd { cmd, env_vars, wrk_dir, input: text.into() };
+        let (timeout, grace) = (self.timeout, self.grace);
+        let cmd = Cmd { cmd, env_vars, wrk_dir, input: text.into(), timeout, grace };
         Ok(cmd)
     }
 
@@ -158,7 +355,7 @@ impl BlackBoxModel {
             // first time run: we store child proces to avoid being killed early
             if self.task.is_none() {
                 let child = cmd.create_child_process()?;
-                self.task = Task(child).into();
+                self.task = Task::new(child, self.grace).into();
             }
             cmd.cmd = int_file.to_owned();
             cmd.run_with_input()?
@@ -168,5 +365,49 @@ impl BlackBoxModel {
 
         Ok(out)
     }
+
+    /// Call run script with `text` as its standard input (stdin), and hand
+    /// over each parsed entry to `f` while the process is still running.
+    /// Return the number of entries.
+    pub(super) fn submit_cmd_streaming<F>(&mut self, text: &str, mut f: F) -> Result<usize>
+    where
+        F: FnMut(Computed) -> Result<()>,
+    {
+        let mut cmd = self.create_onetime_cmd(text)?;
+
+        if let Some(int_file) = &self.int_file {
+            debug!("interactive mode enabled");
+            if self.task.is_none() {
+                let child = cmd.create_child_process()?;
+                self.task = Task::new(child, self.grace).into();
+            }
+            cmd.cmd = int_file.to_owned();
+        }
+
+        // all processes started by the script will be terminated on drop,
+        // even if `f` panics
+        let mut task = Task::new(cmd.spawn_with_input()?, cmd.grace);
+        let watchdog = cmd.watch(&task.child);
+        let stdout = task.child.stdout.take().context("Failed to open stdout")?;
+        let stderr = read_stderr_in_background(&mut task.child);
+        let mut n = 0;
+        let result = ComputedReader::new(std::io::BufReader::new(stdout)).try_for_each(|mp| {
+            n += 1;
+            f(mp?)
+        });
+        // do not leave processes of the script running on error
+        let status = if result.is_err() { task.terminate() } else { task.wait() };
+        let status = status.context("Failed to wait for child process")?;
+        cmd.check_timeout(watchdog)?;
+        let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+        // the script failed on its own, such as crashed after partial output,
+        // which is reported instead of the broken stream
+        if result.is_ok() || !terminated_by_us(status) {
+            cmd.check_status(status, stderr)?;
+        }
+        result?;
+
+        Ok(n)
+    }
 }
 // 5323ec2e ends here
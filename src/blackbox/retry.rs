@@ -0,0 +1,172 @@
+// [[file:../../models.note::*imports][imports:1]]
+use super::*;
+use std::time::Duration;
+// imports:1 ends here
+
+// [[file:../../models.note::3e8a5c21][3e8a5c21]]
+/// Kinds of failure of a blackbox computation that could be retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Run script exited with failure, see [`RunScriptError`]
+    RunScript,
+    /// Run script did not finish in time, see [`TimeoutError`]
+    Timeout,
+    /// SCF not converged, see [`ScfNotConverged`]
+    ScfNotConverged,
+}
+
+impl FailureKind {
+    /// Classify error `e`. Return None for errors not covered, such as
+    /// invalid template or results.
+    pub fn of(e: &Error) -> Option<Self> {
+        if e.downcast_ref::<RunScriptError>().is_some() {
+            Some(Self::RunScript)
+        } else if e.downcast_ref::<TimeoutError>().is_some() {
+            Some(Self::Timeout)
+        } else if e.downcast_ref::<ScfNotConverged>().is_some() {
+            Some(Self::ScfNotConverged)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::str::FromStr for FailureKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "script" => Ok(Self::RunScript),
+            "timeout" => Ok(Self::Timeout),
+            "scf" => Ok(Self::ScfNotConverged),
+            _ => bail!("invalid failure kind: {s:?}"),
+        }
+    }
+}
+
+/// Policy for retrying failed computations of [`BlackBoxModel`]
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one. No retry if
+    /// less than 2.
+    pub max_attempts: usize,
+    /// Delay before the first retry
+    pub backoff: Duration,
+    /// Multiply the delay by this factor for each further retry
+    pub backoff_factor: f64,
+    /// Kinds of failure to be retried
+    pub retry_on: Vec<FailureKind>,
+    /// Retry in a fresh scratch directory, leaving the failed one on disk for
+    /// inspection. Otherwise the scratch directory will be reused, which
+    /// could be useful for restarting from files of the failed attempt.
+    pub fresh_scratch: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Duration::from_secs(1),
+            backoff_factor: 2.0,
+            retry_on: vec![FailureKind::RunScript, FailureKind::Timeout, FailureKind::ScfNotConverged],
+            fresh_scratch: false,
+        }
+    }
+}
+
+// Parse boolean values such as "true" or "1"
+fn parse_bool(key: &str, s: &str) -> Result<bool> {
+    match s.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" => Ok(true),
+        "0" | "false" | "no" => Ok(false),
+        _ => bail!("invalid {key}: {s:?}"),
+    }
+}
+
+impl RetryPolicy {
+    /// Read policy from env vars in .env file:
+    ///
+    /// - `BBM_RETRY_MAX_ATTEMPTS`: the maximum number of attempts
+    /// - `BBM_RETRY_BACKOFF`: delay in seconds before the first retry
+    /// - `BBM_RETRY_ON`: comma separated kinds of failure: script, timeout, scf
+    /// - `BBM_RETRY_FRESH_SCRATCH`: retry in a fresh scratch directory or not
+    pub(super) fn from_envfile(envfile: &envfile::EnvFile) -> Result<Self> {
+        let mut policy = Self::default();
+        if let Some(s) = envfile.get("BBM_RETRY_MAX_ATTEMPTS") {
+            let n = s.trim().parse();
+            policy.max_attempts = n.with_context(|| format!("invalid BBM_RETRY_MAX_ATTEMPTS: {s:?}"))?;
+        }
+        if let Some(t) = parse_seconds("BBM_RETRY_BACKOFF", envfile.get("BBM_RETRY_BACKOFF"))? {
+            policy.backoff = t;
+        }
+        if let Some(s) = envfile.get("BBM_RETRY_ON") {
+            policy.retry_on = s.split(',').map(|x| x.parse()).collect::<Result<_>>()?;
+        }
+        if let Some(s) = envfile.get("BBM_RETRY_FRESH_SCRATCH") {
+            policy.fresh_scratch = parse_bool("BBM_RETRY_FRESH_SCRATCH", s)?;
+        }
+        Ok(policy)
+    }
+
+    /// Return true if computation failed with `e` should be retried.
+    pub fn is_retryable(&self, e: &Error) -> bool {
+        FailureKind::of(e).is_some_and(|k| self.retry_on.contains(&k))
+    }
+
+    // The delay before retry after `attempt` failed, counting from 1.
+    fn delay(&self, attempt: usize) -> Duration {
+        self.backoff.mul_f64(self.backoff_factor.powi(attempt as i32 - 1))
+    }
+}
+// 3e8a5c21 ends here
+
+// [[file:../../models.note::9b47d0e3][9b47d0e3]]
+impl BlackBoxModel {
+    // Leave the scratch directory of a failed attempt on disk, and start over
+    // in a new one for the next call.
+    pub(super) fn detach_scratch_directory(&mut self) -> Option<PathBuf> {
+        // the interactive process is running in the scratch directory
+        self.task = None;
+        let path = self.temp_dir.take()?.keep();
+        self.failed_scratch_dirs.push(path.clone());
+        Some(path)
+    }
+
+    /// Call `f` repeatedly following the retry policy until success. The
+    /// error of the last attempt is returned on failure.
+    pub(super) fn with_retry<T, F>(&mut self, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut Self) -> Result<T>,
+    {
+        let policy = self.retry.clone();
+        let mut attempt = 1;
+        loop {
+            let e = match f(self) {
+                Ok(x) => return Ok(x),
+                Err(e) => e,
+            };
+            // no retry policy set
+            if policy.max_attempts < 2 {
+                return Err(e);
+            }
+
+            let scratch_dir = if policy.fresh_scratch {
+                self.detach_scratch_directory()
+            } else {
+                // in case of renewal required by scratch policy
+                self.last_attempt_failed = true;
+                self.temp_dir.as_ref().map(|d| d.path().to_owned())
+            };
+            warn!("Attempt {attempt} failed: {e:#}. Scratch directory: {scratch_dir:?}");
+            if attempt >= policy.max_attempts || !policy.is_retryable(&e) {
+                return Err(e.context(format!("computation failed after {attempt} attempt(s)")));
+            }
+
+            let delay = policy.delay(attempt);
+            info!("Retry in {delay:?} ...");
+            std::thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+}
+// 9b47d0e3 ends here
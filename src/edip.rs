@@ -11,7 +11,6 @@ use vecfx::*;
 // [[file:../models.note::6e669f3b][6e669f3b]]
 #[derive(Clone, Debug, Default)]
 pub struct Edip {
-    virial: f64,
     // for create neighbors
     nh: Neighborhood,
 }
@@ -29,7 +28,7 @@ impl Edip {
 
 impl ChemicalModel for Edip {
     fn compute(&mut self, mol: &Molecule) -> Result<Computed> {
-        const search_radius: f64 = 4.0;
+        const SEARCH_RADIUS: f64 = 4.0;
 
         // only works for silicon
         let not_silicon = mol.symbols().any(|x| x != "Si");
@@ -46,7 +45,7 @@ impl ChemicalModel for Edip {
         let lat = mol.get_lattice();
         for i in 0..n {
             let mut connected = HashSet::new();
-            for x in self.nh.neighbors(i, search_radius) {
+            for x in self.nh.neighbors(i, SEARCH_RADIUS) {
                 // FIXME: avoid recompute pair distance in edip crate
                 let j = x.node;
                 let pi: Vector3f = positions[i].into();
@@ -73,8 +72,14 @@ impl ChemicalModel for Edip {
         computed.set_energy(energy);
         computed.set_forces(forces);
 
-        // FIXME: could be removed
-        self.virial = virial;
+        // NOTE: EDIP only provides the isotropic part of the virial, which is
+        // used for the diagonal elements
+        let w = [[virial, 0.0, 0.0], [0.0, virial, 0.0], [0.0, 0.0, virial]];
+        computed.set_virial(w);
+        if let Some(lat) = lat {
+            let p = -virial / lat.volume();
+            computed.set_stress([[p, 0.0, 0.0], [0.0, p, 0.0], [0.0, 0.0, p]]);
+        }
 
         Ok(computed)
     }
@@ -98,16 +103,17 @@ fn test_edip() -> Result<()> {
     assert!(dbg!(f_norm) <= 0.005);
     let energy = computed.get_energy().unwrap();
     approx::assert_relative_eq!(energy, -39.57630354331939, epsilon = 1e-5);
-    approx::assert_relative_eq!(model.virial, 0.00224989660978845, epsilon = 1e-5);
+    let virial = computed.get_virial().unwrap();
+    approx::assert_relative_eq!(virial[0][0], 0.00224989660978845, epsilon = 1e-5);
+    assert!(computed.get_stress().is_none());
 
     let f = "./tests/files/si5.xyz";
     let mol = Molecule::from_file(f)?;
     let computed = model.compute(&mol)?;
     let energy = computed.get_energy().unwrap();
     approx::assert_relative_eq!(energy, -14.566606, epsilon = 1e-5);
-    // FIXME: refactor
-    let virial = model.virial;
-    approx::assert_relative_eq!(virial, -3.643552, epsilon = 1e-5);
+    let virial = computed.get_virial().unwrap();
+    approx::assert_relative_eq!(virial[1][1], -3.643552, epsilon = 1e-5);
 
     let f = computed.get_forces().unwrap();
     #[rustfmt::skip]
@@ -125,9 +131,13 @@ fn test_edip() -> Result<()> {
     // for silicon crystal
     let mol = Molecule::from_file("./tests/files/si-3x3x3.cif")?;
     let computed = model.compute(&mol)?;
-    let energy = computed.get_energy().unwrap();
+    let _energy = computed.get_energy().unwrap();
     let f = computed.get_forces().unwrap();
     dbg!(f.as_flat().as_vector_slice().norm());
+    let stress = computed.get_stress().expect("stress for periodic system");
+    let virial = computed.get_virial().unwrap();
+    let vol = mol.get_lattice().unwrap().volume();
+    approx::assert_relative_eq!(stress[2][2], -virial[2][2] / vol, epsilon = 1e-8);
 
     Ok(())
 }
@@ -5,6 +5,8 @@ use gut::prelude::*;
 
 // [[file:../models.note::5d2df595][5d2df595]]
 mod model_properties;
+mod units;
+mod vibration;
 
 mod blackbox;
 mod edip;
@@ -30,9 +32,12 @@ pub trait ChemicalModel: Send {
 // bf8cc73b ends here
 
 // [[file:../models.note::616b7a47][616b7a47]]
-pub use crate::blackbox::BlackBoxModel;
+pub use crate::blackbox::{
+    BlackBoxModel, FailureKind, RetryPolicy, RunScriptError, ScfNotConverged, ScratchPolicy, TimeoutError,
+};
 pub use crate::lj::LennardJones;
 pub use crate::model_properties::*;
+pub use crate::vibration::{Thermochemistry, VibrationalAnalysis};
 
 pub use crate::edip::Edip;
 
@@ -5,6 +5,7 @@
 // [[file:../models.note::5aceffc7][5aceffc7]]
 use super::*;
 
+use gchemol::Lattice;
 use gchemol::Molecule;
 use vecfx::*;
 // 5aceffc7 ends here
@@ -51,10 +52,8 @@ impl LennardJones {
         debug_assert_eq!(n, forces.len(), "positions.len() != forces.len()");
 
         // initialize with zeros
-        for i in 0..n {
-            for j in 0..3 {
-                forces[i][j] = 0.0;
-            }
+        for f in forces.iter_mut() {
+            *f = [0.0; 3];
         }
 
         // collect parts in parallel
@@ -78,7 +77,7 @@ impl LennardJones {
             for k in 0..3 {
                 let dr = positions[j][k] - positions[i][k];
                 forces[i][k] += 1.0 * g * dr;
-                forces[j][k] += -1.0 * g * dr;
+                forces[j][k] -= g * dr;
             }
         }
 
@@ -88,34 +87,47 @@ impl LennardJones {
 // core:1 ends here
 
 // [[file:../models.note::d55b0da4][d55b0da4]]
+/// Cutoff radius of pair interactions in periodic system, in units of sigma
+const PERIODIC_CUTOFF: f64 = 2.5;
+
 impl ChemicalModel for LennardJones {
     fn compute(&mut self, mol: &Molecule) -> Result<Computed> {
-        if mol.lattice.is_some() {
-            warn!("LJ model: periodic lattice will be ignored!");
+        // periodic images within cutoff are considered using minimum image
+        // convention, which requires the cutoff no more than half of the
+        // shortest cell width.
+        let lat = mol.get_lattice();
+        let rc = PERIODIC_CUTOFF * self.sigma;
+        if let Some(lat) = lat {
+            let w = lat.widths().into_iter().fold(f64::INFINITY, f64::min);
+            ensure!(2.0 * rc <= w, "LJ model: cell width {w} is too small for cutoff {rc}");
         }
 
         let natoms = mol.natoms();
         let mut energy = 0.0;
-        let mut forces = Vec::with_capacity(natoms);
-
-        // initialize with zeros
-        for _ in 0..natoms {
-            forces.push([0.0; 3]);
-        }
+        let mut forces = vec![[0.0; 3]; natoms];
+        let mut virial = [[0.0; 3]; 3];
 
         // calculate energy and forces
         let positions: Vec<_> = mol.positions().collect();
-        let dm = get_distance_matrix(&positions);
         for i in 0..natoms {
             for j in 0..i {
-                let r = dm[i][j];
+                let dij = get_displacement(lat, positions[i], positions[j]);
+                let r = dij.vec2norm();
+                if lat.is_some() && r > rc {
+                    continue;
+                }
                 energy += self.pair_energy(r);
                 if self.derivative_order >= 1 {
                     let g = self.pair_gradient(r);
                     for k in 0..3 {
-                        let dr = positions[j][k] - positions[i][k];
-                        forces[i][k] += 1.0 * g * dr / r;
-                        forces[j][k] += -1.0 * g * dr / r;
+                        let dr = dij[k];
+                        forces[i][k] += g * dr / r;
+                        forces[j][k] -= g * dr / r;
+                        // virial: displacement times the force on atom j
+                        // due to atom i
+                        for l in 0..3 {
+                            virial[k][l] -= dr * g * dij[l] / r;
+                        }
                     }
                 }
             }
@@ -126,6 +138,12 @@ impl ChemicalModel for LennardJones {
 
         if self.derivative_order >= 1 {
             computed.set_forces(forces);
+            if let Some(lat) = lat {
+                let vol = lat.volume();
+                let stress = virial.map(|row| row.map(|x| -x / vol));
+                computed.set_virial(virial);
+                computed.set_stress(stress);
+            }
         }
         if self.derivative_order >= 2 {
             unimplemented!();
@@ -135,24 +153,15 @@ impl ChemicalModel for LennardJones {
     }
 }
 
-/// Return all distances between any pair of points
-fn get_distance_matrix(points: &[[f64; 3]]) -> Vec<Vec<f64>> {
-    use gchemol::geom::prelude::*;
-
-    let npts = points.len();
-
-    // fill distance matrix
-    let mut distmat = vec![];
-    for i in 0..npts {
-        let mut dijs = vec![];
-        for j in 0..npts {
-            let dij = points[i].distance(points[j]);
-            dijs.push(dij);
-        }
-        distmat.push(dijs);
+/// Return the displacement vector from point `pi` to point `pj`, obeying the
+/// minimum image convention for periodic system
+fn get_displacement(lat: Option<&Lattice>, pi: [f64; 3], pj: [f64; 3]) -> [f64; 3] {
+    let d = [pj[0] - pi[0], pj[1] - pi[1], pj[2] - pi[2]];
+    if let Some(lat) = lat {
+        lat.apply_mic(d).into()
+    } else {
+        d
     }
-
-    distmat
 }
 // d55b0da4 ends here
 
@@ -163,8 +172,10 @@ fn test_lj_model() {
 
     // gosh_core::gut::cli::setup_logger();
 
-    let mut lj = LennardJones::default();
-    lj.derivative_order = 1;
+    let mut lj = LennardJones {
+        derivative_order: 1,
+        ..Default::default()
+    };
 
     // LJ3
     let mol = Molecule::from_file("tests/files/LennardJones/LJ3.xyz").expect("lj3 test file");
@@ -173,9 +184,9 @@ fn test_lj_model() {
     assert_relative_eq!(-3.0, e, epsilon = 1e-3);
 
     let forces = mr.get_forces().expect("lj model forces: LJ3");
-    for i in 0..mol.natoms() {
-        for j in 0..3 {
-            assert_relative_eq!(0.0, forces[i][j], epsilon = 1e-3);
+    for f in forces.iter() {
+        for &x in f {
+            assert_relative_eq!(0.0, x, epsilon = 1e-3);
         }
     }
 
@@ -186,10 +197,49 @@ fn test_lj_model() {
     assert_relative_eq!(-173.92843, e, epsilon = 1e-3);
 
     let forces = mr.get_forces().expect("lj model forces: LJ3");
-    for i in 0..mol.natoms() {
-        for j in 0..3 {
-            assert_relative_eq!(0.0, forces[i][j], epsilon = 1e-3);
+    for f in forces.iter() {
+        for &x in f {
+            assert_relative_eq!(0.0, x, epsilon = 1e-3);
         }
     }
+    assert!(mr.get_stress().is_none());
+
+    // LJ dimer in a large cubic box, stretched from its equilibrium distance
+    let r0 = 2f64.powf(1.0 / 6.0);
+    let mut mol = Molecule::from_atoms(vec![("X", [0.0; 3]), ("X", [r0 + 0.1, 0.0, 0.0])]);
+    mol.set_lattice(Lattice::new([[10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]]));
+    let mr = lj.compute(&mol).expect("lj model: periodic dimer");
+    let virial = mr.get_virial().expect("lj model virial");
+    let stress = mr.get_stress().expect("lj model stress");
+    // attractive: negative virial along x
+    assert!(virial[0][0] < 0.0);
+    assert_relative_eq!(0.0, virial[1][1], epsilon = 1e-8);
+    assert_relative_eq!(stress[0][0], -virial[0][0] / 1000.0, epsilon = 1e-8);
+
+    // fcc crystal at equilibrium nearest neighbor distance in a 4x4x4
+    // supercell. The reference energy per atom is the sum over the first
+    // four neighbor shells (12, 6, 24 and 12 atoms) within cutoff.
+    let a = 2f64.sqrt() * r0;
+    let basis = [[0.0, 0.0, 0.0], [0.5, 0.5, 0.0], [0.5, 0.0, 0.5], [0.0, 0.5, 0.5]];
+    let mut atoms = vec![];
+    for n in 0..64 {
+        let cell = [n / 16, n / 4 % 4, n % 4];
+        for b in basis {
+            let p = [0, 1, 2].map(|x| (cell[x] as f64 + b[x]) * a);
+            atoms.push(("X", p));
+        }
+    }
+    let mut mol = Molecule::from_atoms(atoms);
+    mol.set_lattice(Lattice::new([[4.0 * a, 0.0, 0.0], [0.0, 4.0 * a, 0.0], [0.0, 0.0, 4.0 * a]]));
+    let mr = lj.compute(&mol).expect("lj model: fcc");
+    let e = mr.get_energy().unwrap() / 256.0;
+    assert_relative_eq!(e, -7.761588139789094, epsilon = 1e-8);
+    for f in mr.get_forces().unwrap() {
+        assert_relative_eq!(f.vec2norm(), 0.0, epsilon = 1e-8);
+    }
+
+    // the cell is too small for cutoff
+    mol.set_lattice(Lattice::new([[a, 0.0, 0.0], [0.0, a, 0.0], [0.0, 0.0, a]]));
+    assert!(lj.compute(&mol).is_err());
 }
 // test:1 ends here
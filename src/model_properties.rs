@@ -1,9 +1,64 @@
+// [[file:../models.note::*header][header:1]]
+//! Model properties computed by chemical models, and the text format for
+//! exchanging them with external programs.
+//!
+//! # Format
+//!
+//! Records are written in `@`-prefixed sections, such as `@energy` or
+//! `@forces`. Lines starting with `#` and blank lines are ignored. Each entry
+//! starts with a format version header:
+//!
+//! ```text
+//! @model_properties_format_version 0.2
+//! @energy
+//! -0.32933619218901E+00
+//! @end
+//! ```
+//!
+//! * version 0.1: entries are separated only by version headers, which could
+//!   also be spelled as `@model_properties_file_format_version`.
+//! * version 0.2: each entry must be terminated explicitly with `@end`.
+//!
+//! Lattice vectors of periodic structure are given in `@lattice` section, one
+//! vector per line. Atom positions in `@structure` can be given in fractional
+//! coordinates with `@structure fractional=true`.
+//!
+//! Provenance metadata can be given as `key=value` pairs in `@meta` section,
+//! in header (`@meta program=orca version=5.0`) or one pair per line.
+//!
+//! SCF information can be reported in `@orbital_energies` (energy with optional
+//! occupation number per line), `@scf_converged` and `@scf_iterations`
+//! sections.
+//!
+//! Energy gradient can be given in `@gradient` section instead of `@forces`,
+//! which is converted into forces on parsing.
+//!
+//! Energy and forces of electronic states can be indexed using `state` option,
+//! such as `@energy state=2`. The active state (root) is specified in
+//! `@active_state` section.
+//!
+//! # JSON
+//!
+//! `Computed` can also be serialized into JSON, with keys named after the
+//! sections above in canonical units. The schema is versioned in
+//! `schema_version` key (currently "1.0"), and the structure is written as
+//! an object with `symbols`, Cartesian `positions` and optional `lattice`
+//! vectors:
+//!
+//! ```text
+//! {"schema_version": "1.0", "energy": -1.0,
+//!  "structure": {"symbols": ["H", "H"], "positions": [[0, 0, 0], [0, 0, 0.74]]}}
+//! ```
+// header:1 ends here
+
 // [[file:../models.note::b456354a][b456354a]]
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::io::BufRead;
 use std::str::FromStr;
 
 use super::*;
+use crate::units::{conversion_factor, Dimension};
 
 use gchemol::prelude::*;
 use gchemol::Atom;
@@ -11,53 +66,394 @@ use gchemol::Lattice;
 use gchemol::Molecule;
 // b456354a ends here
 
+// [[file:../models.note::a6f2c0d9][a6f2c0d9]]
+mod ops;
+// a6f2c0d9 ends here
+
 // [[file:../models.note::7de724a0][7de724a0]]
-const MODEL_PROPERTIES_FORMAT_VERSION: &str = "0.1";
+const MODEL_PROPERTIES_FORMAT_VERSION: &str = "0.2";
+const SUPPORTED_FORMAT_VERSIONS: [&str; 2] = ["0.1", "0.2"];
 
 /// The computed model properties by external application
+///
+/// All properties are stored in canonical units: eV for energy, Å for
+/// length, eV/Å for forces, eV/Å^2 for Hessian and force constants, eV/Å^3
+/// for stress, e·Å for dipole moment, Å^3 for polarizability, and e for
+/// charges. Values in other units can be converted on parsing using `unit`
+/// option in section header, e.g. `@forces unit=hartree/bohr`.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Computed {
+    /// The version of JSON schema, which is always written out
+    #[serde(flatten)]
+    version: SchemaVersion,
     energy: Option<f64>,
     forces: Option<Vec<[f64; 3]>>,
     dipole: Option<[f64; 3]>,
-    #[serde(skip_deserializing, skip_serializing)]
+    #[serde(
+        default,
+        rename = "structure",
+        serialize_with = "serialize_structure",
+        deserialize_with = "deserialize_structure"
+    )]
     molecule: Option<Molecule>,
-    #[serde(skip_deserializing, skip_serializing)]
-    force_constants: Option<Vec<[f64; 3]>>,
+    /// 3x3 polarizability tensor
+    polarizability: Option<[[f64; 3]; 3]>,
+    /// Derivatives of dipole moment wrt atom displacements, in 3Nx3
+    dipole_derivatives: Option<Vec<[f64; 3]>>,
+    /// 3x3 stress tensor, in eV/Å^3
+    stress: Option<[[f64; 3]; 3]>,
+    /// 3x3 virial tensor, in eV
+    virial: Option<[[f64; 3]; 3]>,
+    /// Symmetric 3Nx3N Hessian matrix (force constants), stored as packed
+    /// lower triangle in row-major order
+    hessian: Option<Vec<f64>>,
+    /// Partial charges of atoms
+    charges: Option<Vec<f64>>,
+    /// Spin populations of atoms
+    spins: Option<Vec<f64>>,
+    /// Energy contributions of atoms
+    atomic_energies: Option<Vec<f64>>,
+    /// Unrecognized sections, such as program-specific data
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    extra: BTreeMap<String, ExtraSection>,
+    /// Energies and forces of indexed electronic states
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    states: BTreeMap<usize, ElectronicState>,
+    /// The index of active state (root) for `get_energy` and `get_forces`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    active_state: Option<usize>,
+    /// The record from which unindexed forces were obtained
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    forces_source: Option<ForcesSource>,
+    /// Named components of total energy, such as dispersion or solvation
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    energy_components: BTreeMap<String, f64>,
+    /// Energies of molecular orbitals (or bands), in eV
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    orbital_energies: Option<Vec<f64>>,
+    /// Occupation numbers of molecular orbitals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    orbital_occupations: Option<Vec<f64>>,
+    /// Whether SCF iterations converged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scf_converged: Option<bool>,
+    /// The number of SCF iterations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scf_iterations: Option<usize>,
+    /// Provenance metadata, such as program name and version, host or wall
+    /// time
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    meta: BTreeMap<String, String>,
+}
+
+/// The record produced by the engine for forces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ForcesSource {
+    /// Forces in `@forces` record
+    Forces,
+    /// Energy gradient in `@gradient` record, converted to forces
+    Gradient,
+    /// Both `@forces` and `@gradient` records, which agree with each other
+    Both,
+}
+
+/// Energy and forces of an electronic state
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct ElectronicState {
+    energy: Option<f64>,
+    forces: Option<Vec<[f64; 3]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    forces_source: Option<ForcesSource>,
 }
 // 7de724a0 ends here
 
+// [[file:../models.note::9b7f3e2a][9b7f3e2a]]
+/// The version of JSON schema for Computed. Minor version bumps are backward
+/// compatible.
+const JSON_SCHEMA_VERSION: &str = "1.0";
+
+/// The `schema_version` key of Computed in JSON, which is always written
+/// as [`JSON_SCHEMA_VERSION`]. Missing version is accepted on reading.
+#[derive(Debug, Clone, Copy, Default)]
+struct SchemaVersion;
+
+impl Serialize for SchemaVersion {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("schema_version", JSON_SCHEMA_VERSION)?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemaVersion {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Raw {
+            schema_version: Option<String>,
+        }
+
+        let Some(version) = Raw::deserialize(deserializer)?.schema_version else {
+            return Ok(Self);
+        };
+        let major = |v: &str| v.split('.').next().map(|x| x.to_owned());
+        if major(&version) != major(JSON_SCHEMA_VERSION) {
+            let msg = format!("unsupported schema version {version:?}, expect {JSON_SCHEMA_VERSION}");
+            return Err(serde::de::Error::custom(msg));
+        }
+        Ok(Self)
+    }
+}
+
+/// Plain representation of molecular structure in JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StructureData {
+    symbols: Vec<String>,
+    /// Cartesian coordinates in Å
+    positions: Vec<[f64; 3]>,
+    /// Three lattice vectors for periodic system
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lattice: Option<[[f64; 3]; 3]>,
+}
+
+impl StructureData {
+    fn from_molecule(mol: &Molecule) -> Self {
+        Self {
+            symbols: mol.symbols().map(|s| s.to_string()).collect(),
+            positions: mol.positions().collect(),
+            lattice: mol.get_lattice().map(|lat| lat.vectors().map(|v| [v[0], v[1], v[2]])),
+        }
+    }
+
+    fn to_molecule(&self) -> Result<Molecule> {
+        let n = self.symbols.len();
+        let m = self.positions.len();
+        ensure!(n == m, "structure: found {n} symbols, but {m} positions");
+
+        let atoms = self.symbols.iter().zip(self.positions.iter().copied());
+        let mut mol = Molecule::from_atoms(atoms);
+        if let Some(lat) = self.lattice {
+            mol.set_lattice(Lattice::new(lat));
+        }
+        Ok(mol)
+    }
+}
+
+fn serialize_structure<S>(mol: &Option<Molecule>, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    mol.as_ref().map(StructureData::from_molecule).serialize(serializer)
+}
+
+fn deserialize_structure<'de, D>(deserializer: D) -> std::result::Result<Option<Molecule>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let data: Option<StructureData> = Option::deserialize(deserializer)?;
+    data.map(|d| d.to_molecule()).transpose().map_err(serde::de::Error::custom)
+}
+
+// Return true if `stream` looks like JSON document or JSON lines
+fn is_json_stream(stream: &str) -> bool {
+    let s = stream.trim_start();
+    s.starts_with('{') || s.starts_with('[')
+}
+
+// Return true if `stream` contains more than one line, and the first one is a
+// complete JSON value.
+fn is_json_lines(stream: &str) -> bool {
+    let mut lines = stream.lines().filter(|l| !l.trim().is_empty());
+    let first = lines.next().unwrap_or_default();
+    lines.next().is_some() && serde_json::from_str::<serde_json::Value>(first).is_ok()
+}
+
+// Parse entries of Computed from a JSON document (an object or an array of
+// objects) or JSON lines (one object per line).
+fn parse_model_results_json(stream: &str) -> Result<Vec<Computed>> {
+    let all = if stream.trim_start().starts_with('[') {
+        serde_json::from_str(stream).context("invalid JSON array of model properties")?
+    } else if !is_json_lines(stream) {
+        // a single object, possibly in multiple lines
+        vec![serde_json::from_str(stream).context("invalid JSON model properties")?]
+    } else {
+        let mut all = vec![];
+        for (i, line) in stream.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mp: Computed = serde_json::from_str(line)
+                .with_context(|| format!("invalid JSON model properties in line {}: {line:?}", i + 1))?;
+            all.push(mp);
+        }
+        all
+    };
+
+    all.into_iter().map(validate_parsed).collect()
+}
+// 9b7f3e2a ends here
+
+// [[file:../models.note::0c7d1e52][0c7d1e52]]
+/// Unrecognized section in model properties, which is kept as raw text lines
+/// and could be read as typed data on demand.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtraSection {
+    /// The unit factor for numeric values
+    pub unit_factor: f64,
+    /// Other options in section header, in key=value pairs
+    pub options: BTreeMap<String, String>,
+    /// Raw text lines in section
+    pub lines: Vec<String>,
+}
+
+impl Default for ExtraSection {
+    fn default() -> Self {
+        Self {
+            unit_factor: 1.0,
+            options: BTreeMap::new(),
+            lines: vec![],
+        }
+    }
+}
+
+impl ExtraSection {
+    /// Construct from a scalar value.
+    pub fn from_scalar(x: f64) -> Self {
+        Self::from_vector(&[x])
+    }
+
+    /// Construct from a list of values, one value per line.
+    pub fn from_vector(values: &[f64]) -> Self {
+        let lines = values.iter().map(|x| format!("{:-20.12E}", x)).collect();
+        Self { lines, ..Default::default() }
+    }
+
+    /// Construct from a list of xyz components, such as forces.
+    pub fn from_xyz(values: &[[f64; 3]]) -> Self {
+        let lines = values
+            .iter()
+            .map(|[x, y, z]| format!("{:-20.12E} {:-20.12E} {:-20.12E}", x, y, z))
+            .collect();
+        Self { lines, ..Default::default() }
+    }
+
+    /// Return the value of header option `key`.
+    pub fn get_option(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(|v| v.as_str())
+    }
+
+    /// Read data as a scalar value.
+    pub fn as_scalar(&self) -> Result<f64> {
+        let values = self.as_vector()?;
+        ensure!(values.len() == 1, "expect one scalar value, but found {}", values.len());
+        Ok(values[0])
+    }
+
+    // numbered lines for parsing, counting from section start
+    fn numbered_lines(&self) -> Vec<Line<'_>> {
+        self.lines.iter().enumerate().map(|(i, l)| (i + 1, l.as_str())).collect()
+    }
+
+    /// Read data as a list of values, regardless of line layout.
+    pub fn as_vector(&self) -> Result<Vec<f64>> {
+        let values = parse_values(&self.numbered_lines(), self.unit_factor)?;
+        Ok(values)
+    }
+
+    /// Read data as a list of xyz components in Nx3.
+    pub fn as_xyz(&self) -> Result<Vec<[f64; 3]>> {
+        let rows = parse_xyz_lines(&self.numbered_lines(), self.unit_factor)?;
+        Ok(rows)
+    }
+}
+// 0c7d1e52 ends here
+
 // [[file:../models.note::3b493716][3b493716]]
 #[derive(Debug, Clone)]
 struct Header {
     name: String,
     unit_factor: f64,
+    /// Named physical unit of values, such as hartree/bohr
+    unit: Option<String>,
+    /// Other options in key=value pairs
+    options: HashMap<String, String>,
 }
 
 impl FromStr for Header {
     type Err = gut::prelude::Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        if s.starts_with("@") {
+        if let Some(s) = s.strip_prefix('@') {
             let mut unit_factor = 1.0;
-            let parts = &s[1..].split_whitespace().collect_vec();
-            let name = parts[0].into();
+            let mut unit = None;
+            let mut options = HashMap::new();
+            let parts = &s.split_whitespace().collect_vec();
+            let name = parts.first().context("missing section name")?.to_string();
             if parts.len() > 1 {
                 for p in &parts[1..] {
                     if let Some((k, v)) = p.split_once('=') {
                         if k == "unit_factor" {
                             unit_factor = v.parse::<f64>()?;
+                        } else if k == "unit" {
+                            unit = Some(v.to_string());
+                        } else {
+                            options.insert(k.to_string(), v.to_string());
                         }
                     }
                 }
             }
-            Ok(Self { name, unit_factor })
+            Ok(Self {
+                name,
+                unit_factor,
+                unit,
+                options,
+            })
         } else {
             bail!("invalid model properties section header: {}", s);
         }
     }
 }
 
+impl Header {
+    // Return the factor for converting values in physical dimension `dim`
+    // into canonical units.
+    fn factor_for(&self, dim: Dimension) -> Result<f64> {
+        if let Some(unit) = &self.unit {
+            let f = conversion_factor(dim, unit).with_context(|| format!("invalid unit for @{}", self.name))?;
+            Ok(self.unit_factor * f)
+        } else {
+            Ok(self.unit_factor)
+        }
+    }
+}
+
+// Return the physical dimension of values in section `name`, or None for
+// unrecognized section.
+fn section_dimension(name: &str) -> Option<Dimension> {
+    use Dimension::*;
+
+    let dim = match name {
+        "energy" | "atomic_energies" | "energy_components" | "orbital_energies" | "virial" => Energy,
+        "structure" => Length,
+        "forces" | "gradient" => Force,
+        "force_constants" | "hessian" => Hessian,
+        "stress" => Stress,
+        "polarizability" => Volume,
+        "dipole" => Dipole,
+        "dipole_derivatives" | "charges" | "spins" => Charge,
+        _ => return None,
+    };
+    Some(dim)
+}
+
 #[test]
 fn test_header() {
     let s = "@forces ";
@@ -72,12 +468,22 @@ fn test_header() {
     let s = "@forces unit_factor=-1 test=2";
     let h: Header = s.parse().unwrap();
     assert_eq!(h.unit_factor, -1.0);
+    assert_eq!(h.options["test"], "2");
+
+    let s = "@forces unit=hartree/bohr unit_factor=-1";
+    let h: Header = s.parse().unwrap();
+    assert_eq!(h.unit.as_deref(), Some("hartree/bohr"));
+    let f = h.factor_for(Dimension::Force).unwrap();
+    vecfx::approx::assert_relative_eq!(f, -51.42206747632589, epsilon = 1e-8);
+    assert!(h.factor_for(Dimension::Energy).is_err());
 }
 // 3b493716 ends here
 
 // [[file:../models.note::37f15603][37f15603]]
 impl Computed {
-    /// Parse mulitple entries of Computed from string slice
+    /// Parse mulitple entries of Computed from string slice, in the
+    /// `@`-section text format, or in JSON format (a JSON object, an array of
+    /// objects, or JSON lines).
     pub fn parse_all(output: &str) -> Result<Vec<Computed>> {
         parse_model_results(output)
     }
@@ -85,19 +491,33 @@ impl Computed {
     /// Return true if there is no useful properties
     pub fn is_empty(&self) -> bool {
         //self.energy.is_none() && self.forces.is_none() && self.molecule.is_none()
-        self.energy.is_none() && self.forces.is_none()
+        self.energy.is_none() && self.forces.is_none() && self.states.is_empty()
     }
 }
 
 impl fmt::Display for Computed {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut txt = format!("@model_properties_format_version {}\n", MODEL_PROPERTIES_FORMAT_VERSION);
+        // metadata
+        if !self.meta.is_empty() {
+            txt.push_str("@meta\n");
+            for (k, v) in &self.meta {
+                txt.push_str(&format!("{k}={v}\n"));
+            }
+        }
 
         // structure
         if let Some(mol) = &self.molecule {
             txt.push_str("@structure\n");
-            let coords = mol.format_as("text/pxyz").expect("formatted molecule");
-            txt.push_str(&coords);
+            for (_, a) in mol.atoms() {
+                let [x, y, z] = a.position();
+                txt.push_str(&format!("{:6} {:-18.10} {:-18.10} {:-18.10}\n", a.symbol(), x, y, z));
+            }
+            if let Some(lat) = mol.get_lattice() {
+                txt.push_str("@lattice\n");
+                let vectors = lat.vectors().map(|v| [v[0], v[1], v[2]]);
+                txt.push_str(&format_tensor(&vectors));
+            }
         }
         // energy
         if let Some(energy) = &self.energy {
@@ -106,23 +526,146 @@ impl fmt::Display for Computed {
         }
         // forces
         if let Some(forces) = &self.forces {
-            txt.push_str("@forces\n");
-            for [fx, fy, fz] in forces {
-                let line = format!("{:-20.12E} {:-20.12E} {:-20.12E}\n", fx, fy, fz);
-                txt.push_str(&line);
+            txt.push_str(&format_forces(forces, self.forces_source, ""));
+        }
+        // energies and forces of electronic states
+        for (i, state) in &self.states {
+            if let Some(energy) = state.energy {
+                txt.push_str(&format!("@energy state={i}\n{:-20.12E}\n", energy));
+            }
+            if let Some(forces) = &state.forces {
+                txt.push_str(&format_forces(forces, state.forces_source, &format!(" state={i}")));
             }
         }
+        if let Some(i) = self.active_state {
+            txt.push_str(&format!("@active_state\n{i}\n"));
+        }
         // dipole moments
         if let Some(d) = &self.dipole {
             txt.push_str("@dipole\n");
             let line = format!("{:-20.12E} {:-20.12E} {:-20.12E}\n", d[0], d[1], d[2]);
             txt.push_str(&line);
         }
+        // electronic structure
+        if let Some(energies) = &self.orbital_energies {
+            txt.push_str("@orbital_energies\n");
+            match &self.orbital_occupations {
+                Some(occupations) => {
+                    for (e, occ) in energies.iter().zip(occupations) {
+                        txt.push_str(&format!("{:-20.12E} {:-10.6}\n", e, occ));
+                    }
+                }
+                None => {
+                    for e in energies {
+                        txt.push_str(&format!("{:-20.12E}\n", e));
+                    }
+                }
+            }
+        }
+        if let Some(converged) = self.scf_converged {
+            txt.push_str(&format!("@scf_converged\n{converged}\n"));
+        }
+        if let Some(n) = self.scf_iterations {
+            txt.push_str(&format!("@scf_iterations\n{n}\n"));
+        }
+        // energy components
+        if !self.energy_components.is_empty() {
+            txt.push_str("@energy_components\n");
+            for (name, e) in &self.energy_components {
+                txt.push_str(&format!("{name} {:-20.12E}\n", e));
+            }
+        }
+        // per-atom properties
+        for (name, values) in [
+            ("charges", &self.charges),
+            ("spins", &self.spins),
+            ("atomic_energies", &self.atomic_energies),
+        ] {
+            if let Some(values) = values {
+                txt.push_str(&format!("@{name}\n"));
+                for x in values {
+                    txt.push_str(&format!("{:-20.12E}\n", x));
+                }
+            }
+        }
+        // polarizability tensor
+        if let Some(p) = &self.polarizability {
+            txt.push_str("@polarizability\n");
+            txt.push_str(&format_tensor(p));
+        }
+        // dipole derivatives
+        if let Some(dd) = &self.dipole_derivatives {
+            txt.push_str("@dipole_derivatives\n");
+            for [dx, dy, dz] in dd {
+                let line = format!("{:-20.12E} {:-20.12E} {:-20.12E}\n", dx, dy, dz);
+                txt.push_str(&line);
+            }
+        }
+        // stress tensor
+        if let Some(stress) = &self.stress {
+            txt.push_str("@stress\n");
+            txt.push_str(&format_tensor(stress));
+        }
+        // virial tensor
+        if let Some(virial) = &self.virial {
+            txt.push_str("@virial\n");
+            txt.push_str(&format_tensor(virial));
+        }
+        // hessian: packed lower triangle, 3 values per line
+        if let Some(h) = &self.hessian {
+            txt.push_str("@hessian packed=true\n");
+            for chunk in h.chunks(3) {
+                let line = chunk.iter().map(|x| format!("{:-20.12E}", x)).join(" ");
+                txt.push_str(&line);
+                txt.push('\n');
+            }
+        }
+
+        // unrecognized sections
+        for (name, section) in &self.extra {
+            txt.push_str(&format!("@{name}"));
+            if section.unit_factor != 1.0 {
+                txt.push_str(&format!(" unit_factor={}", section.unit_factor));
+            }
+            for (k, v) in &section.options {
+                txt.push_str(&format!(" {k}={v}"));
+            }
+            txt.push('\n');
+            for line in &section.lines {
+                txt.push_str(line);
+                txt.push('\n');
+            }
+        }
+        txt.push_str("@end\n");
 
         write!(f, "{}", txt)
     }
 }
 
+// format forces in @forces or @gradient record according to its `source`,
+// with `options` in the header
+fn format_forces(forces: &[[f64; 3]], source: Option<ForcesSource>, options: &str) -> String {
+    let format_xyz = |sign: f64| -> String {
+        let lines = forces.iter().map(|v| v.map(|x| sign * x));
+        lines.map(|[x, y, z]| format!("{:-20.12E} {:-20.12E} {:-20.12E}\n", x, y, z)).collect()
+    };
+    let mut txt = String::new();
+    if source != Some(ForcesSource::Gradient) {
+        txt.push_str(&format!("@forces{options}\n{}", format_xyz(1.0)));
+    }
+    if matches!(source, Some(ForcesSource::Gradient | ForcesSource::Both)) {
+        txt.push_str(&format!("@gradient{options}\n{}", format_xyz(-1.0)));
+    }
+    txt
+}
+
+// format 3x3 tensor in three lines
+fn format_tensor(t: &[[f64; 3]; 3]) -> String {
+    t.iter()
+        .map(|[x, y, z]| format!("{:-20.12E} {:-20.12E} {:-20.12E}\n", x, y, z))
+        .collect()
+}
+
 impl FromStr for Computed {
     type Err = gut::prelude::Error;
 
@@ -138,166 +681,1277 @@ impl FromStr for Computed {
     }
 }
 
+// a line of text with its line number (1-based)
+type Line<'a> = (usize, &'a str);
+
+/// Error in parsing model properties, locating the offending text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The name of section containing the offending text, such as "forces"
+    pub section: String,
+    /// Line number (1-based) of the offending text in the parsed stream
+    pub line: usize,
+    /// The offending text
+    pub text: String,
+    /// The reason of failure
+    pub reason: String,
+}
+
+impl ParseError {
+    fn new<S: Into<String>>(line: Line, reason: S) -> Self {
+        Self {
+            section: String::new(),
+            line: line.0,
+            text: line.1.into(),
+            reason: reason.into(),
+        }
+    }
+
+    fn in_section(mut self, name: &str) -> Self {
+        self.section = name.into();
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid @{} record in line {}: {} (found {:?})",
+            self.section, self.line, self.reason, self.text
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 // parse a single entry of Computed
-fn parse_model_results_single(part: &[&str]) -> Result<Computed> {
-    // collect records as header separated lines
-    // blank lines are ignored
-    let mut records: HashMap<&str, Vec<&str>> = HashMap::new();
-    let mut header = None;
-    for line in part {
+fn parse_model_results_single(part: &[Line]) -> Result<Computed> {
+    // collect records as header separated lines in order
+    // repeated sections are merged
+    let mut records: Vec<(Line, Vec<Line>)> = vec![];
+    let mut current = None;
+    for &(i, line) in part {
         let line = line.trim();
-        if line.starts_with("@") {
-            // header = line.split_whitespace().next();
-            header = line.trim_end().into();
+        if line.starts_with('@') {
+            let k = records.iter().position(|(h, _)| h.1 == line).unwrap_or_else(|| {
+                records.push(((i, line), vec![]));
+                records.len() - 1
+            });
+            current = Some(k);
+        } else if let Some(k) = current {
+            records[k].1.push((i, line));
         } else {
-            if let Some(k) = header {
-                records.entry(k).or_insert(vec![]).push(line);
-            }
+            debug!("ignored line {i} without section header: {line:?}");
         }
     }
 
     // parse record values
-    if records.len() < 1 {
+    if records.is_empty() {
         warn!("Collected no results. Please check if the stream is clean!");
         warn!("suspicious part: {:?}", part);
     }
 
+    // lattice is required before parsing structure in fractional coordinates,
+    // and structure is required for checking the dimensions of other records
+    records.sort_by_key(|(h, _)| match h.1.split_whitespace().next() {
+        Some("@lattice") => 0,
+        Some("@structure") => 1,
+        _ => 2,
+    });
+
     let mut results = Computed::default();
-    for (k, lines) in records {
-        let header: Header = k.parse()?;
-        let unit_factor = header.unit_factor;
-        match header.name.as_str() {
-            "energy" => {
-                assert_eq!(1, lines.len(), "expect one line containing energy");
-                let energy = lines[0].trim().parse::<f64>()? * unit_factor;
-                results.energy = Some(energy);
-            }
-            "forces" => {
-                let mut forces: Vec<[f64; 3]> = vec![];
-                for line in lines {
-                    let parts: Vec<_> = line.split_whitespace().collect();
-                    if parts.len() != 3 {
-                        bail!("expect xyz forces: {}", line);
-                    }
-                    let fx = parts[0].parse::<f64>()? * unit_factor;
-                    let fy = parts[1].parse::<f64>()? * unit_factor;
-                    let fz = parts[2].parse::<f64>()? * unit_factor;
-                    forces.push([fx, fy, fz]);
-                }
+    let mut lattice = None;
+    for (hline, lines) in records {
+        let header: Header = hline.1.parse().map_err(|e| {
+            let name = hline.1.split_whitespace().next().unwrap_or_default();
+            ParseError::new(hline, format!("{e}")).in_section(name.trim_start_matches('@'))
+        })?;
+        let name = header.name.clone();
+        if name == "lattice" {
+            let unit_factor = header
+                .factor_for(Dimension::Length)
+                .map_err(|e| ParseError::new(hline, format!("{e:#}")).in_section(&name))?;
+            let vectors = parse_tensor(hline, &lines, unit_factor).map_err(|e| e.in_section(&name))?;
+            lattice = Some((hline, Lattice::new(vectors)));
+            continue;
+        }
+        let lat = lattice.as_ref().map(|(_, lat)| lat);
+        parse_record(&mut results, lat, header, hline, &lines).map_err(|e| e.in_section(&name))?;
+    }
+    if let Some((hline, lat)) = lattice {
+        match results.molecule.as_mut() {
+            Some(mol) => mol.set_lattice(lat),
+            None => {
+                let e = ParseError::new(hline, "no structure for lattice").in_section("lattice");
+                return Err(e.into());
+            }
+        }
+    }
+
+    validate_parsed(results)
+}
+
+// parse a record into `results` with its `header` and data `lines`
+fn parse_record(
+    results: &mut Computed,
+    lattice: Option<&Lattice>,
+    header: Header,
+    hline: Line,
+    lines: &[Line],
+) -> Result<(), ParseError> {
+    let unit_factor = match section_dimension(&header.name) {
+        Some(dim) => header.factor_for(dim).map_err(|e| ParseError::new(hline, format!("{e:#}")))?,
+        None => header.unit_factor,
+    };
+    let nlines = lines.len();
+    let expect_one_line = |what: &str| {
+        if nlines == 1 {
+            Ok(())
+        } else {
+            Err(ParseError::new(hline, format!("expect one line containing {what}, but found {nlines}")))
+        }
+    };
+
+    // the index of electronic state for energy or forces
+    let stateful = matches!(header.name.as_str(), "energy" | "forces" | "gradient");
+    let state = match header.options.get("state") {
+        Some(i) if stateful => match i.parse::<usize>() {
+            Ok(i) => Some(i),
+            Err(_) => return Err(ParseError::new(hline, format!("invalid state index {i:?}"))),
+        },
+        Some(_) if section_dimension(&header.name).is_some() => {
+            return Err(ParseError::new(hline, "state option is only supported for energy and forces"));
+        }
+        _ => None,
+    };
 
-                results.forces = Some(forces);
+    match header.name.as_str() {
+        "energy" => {
+            expect_one_line("energy")?;
+            let values = parse_values(lines, unit_factor)?;
+            if values.len() != 1 {
+                return Err(ParseError::new(lines[0], "expect one value of energy"));
+            }
+            match state {
+                Some(i) => results.states.entry(i).or_default().energy = Some(values[0]),
+                None => results.energy = Some(values[0]),
+            }
+        }
+        "forces" => {
+            let forces = parse_xyz_lines(lines, unit_factor)?;
+            let source = ForcesSource::Forces;
+            merge_forces(results, state, forces, source).map_err(|e| ParseError::new(hline, e))?;
+        }
+        "gradient" => {
+            let forces = parse_xyz_lines(lines, -unit_factor)?;
+            let source = ForcesSource::Gradient;
+            merge_forces(results, state, forces, source).map_err(|e| ParseError::new(hline, e))?;
+        }
+        "active_state" => {
+            expect_one_line("the index of active state")?;
+            let i = lines[0]
+                .1
+                .parse()
+                .map_err(|_| ParseError::new(lines[0], "invalid index of active state"))?;
+            results.active_state = Some(i);
+        }
+        "structure" => {
+            let mut s = lines.iter().map(|l| l.1).join("\n");
+            s.push_str("\n\n");
+            let mut mol =
+                Molecule::from_str(&s, "text/pxyz").map_err(|e| ParseError::new(hline, format!("{e:#}")))?;
+            // fractional coordinates are not affected by length unit
+            let fractional = header.options.get("fractional").is_some_and(|v| v == "true");
+            if unit_factor != 1.0 {
+                if !fractional {
+                    let positions = mol.positions().map(|p| p.map(|x| x * unit_factor)).collect_vec();
+                    mol.set_positions(positions);
+                }
+                // lattice vectors in TV lines
+                if let Some(lat) = mol.get_lattice() {
+                    let vectors = lat.vectors().map(|v| [v[0], v[1], v[2]].map(|x| x * unit_factor));
+                    mol.set_lattice(Lattice::new(vectors));
+                }
+            }
+            if fractional {
+                let lat = *lattice
+                    .or(mol.get_lattice())
+                    .ok_or_else(|| ParseError::new(hline, "fractional coordinates require lattice"))?;
+                let fractional = mol.positions().collect_vec();
+                mol.set_lattice(lat);
+                mol.set_scaled_positions(fractional);
+            }
+            results.molecule = Some(mol);
+        }
+        "dipole" => {
+            expect_one_line("dipole moment")?;
+            let d = parse_xyz_lines(lines, unit_factor)?;
+            results.dipole = Some(d[0]);
+        }
+        "polarizability" | "dipole_derivatives" | "force_constants" => {
+            // optional records emitted by adaptors in various layouts, which
+            // are kept as extra data if not recognized
+            if !parse_response_record(results, &header.name, lines, unit_factor)? {
+                keep_extra_section(results, header, lines);
             }
-            "structure" => {
-                let mut s = lines.join("\n");
-                s.push_str("\n\n");
-                let mol = Molecule::from_str(&s, "text/pxyz")?;
-                results.molecule = Some(mol);
+        }
+        "stress" => {
+            let stress = parse_tensor(hline, lines, unit_factor)?;
+            results.stress = Some(stress);
+        }
+        "virial" => {
+            let virial = parse_tensor(hline, lines, unit_factor)?;
+            results.virial = Some(virial);
+        }
+        "charges" => {
+            results.charges = parse_values(lines, unit_factor)?.into();
+        }
+        "spins" => {
+            results.spins = parse_values(lines, unit_factor)?.into();
+        }
+        "atomic_energies" => {
+            results.atomic_energies = parse_values(lines, unit_factor)?.into();
+        }
+        "orbital_energies" => {
+            // one or two columns: energy and optional occupation
+            let ncols = lines.first().map(|l| l.1.split_whitespace().count()).unwrap_or(1);
+            let mut energies = vec![];
+            let mut occupations = vec![];
+            for &line in lines {
+                let parts = line.1.split_whitespace().collect_vec();
+                if parts.len() != ncols || ncols > 2 {
+                    return Err(ParseError::new(line, "expect orbital energy and optional occupation"));
+                }
+                energies.push(parse_f64(line, parts[0], unit_factor)?);
+                if ncols == 2 {
+                    occupations.push(parse_f64(line, parts[1], 1.0)?);
+                }
             }
-            "dipole" => {
-                assert_eq!(1, lines.len(), "expect one line containing dipole moment");
-                let parts: Vec<_> = lines[0].split_whitespace().collect();
-                let fx = parts[0].parse::<f64>()? * unit_factor;
-                let fy = parts[1].parse::<f64>()? * unit_factor;
-                let fz = parts[2].parse::<f64>()? * unit_factor;
-                results.dipole = Some([fx, fy, fz]);
+            results.orbital_energies = Some(energies);
+            results.orbital_occupations = (ncols == 2).then_some(occupations);
+        }
+        "scf_converged" => {
+            expect_one_line("SCF convergence")?;
+            let converged = match lines[0].1 {
+                "true" | "True" | "T" | "yes" => true,
+                "false" | "False" | "F" | "no" => false,
+                _ => return Err(ParseError::new(lines[0], "expect true or false")),
+            };
+            results.scf_converged = Some(converged);
+        }
+        "scf_iterations" => {
+            expect_one_line("the number of SCF iterations")?;
+            let n = lines[0]
+                .1
+                .parse()
+                .map_err(|_| ParseError::new(lines[0], "invalid number of SCF iterations"))?;
+            results.scf_iterations = Some(n);
+        }
+        "meta" => {
+            // key=value pairs in header or in lines
+            results.meta.extend(header.options.iter().map(|(k, v)| (k.clone(), v.clone())));
+            for &line in lines {
+                let (k, v) = line
+                    .1
+                    .split_once('=')
+                    .ok_or_else(|| ParseError::new(line, "expect key=value pair"))?;
+                results.meta.insert(k.trim().into(), v.trim().into());
             }
-            _ => {
-                warn!("ignored record: {:?}", k);
+        }
+        "energy_components" => {
+            for &line in lines {
+                let parts = line.1.split_whitespace().collect_vec();
+                if parts.len() != 2 {
+                    return Err(ParseError::new(line, "expect name and value of energy component"));
+                }
+                let e = parse_f64(line, parts[1], unit_factor)?;
+                if results.energy_components.insert(parts[0].into(), e).is_some() {
+                    return Err(ParseError::new(line, "duplicated energy component"));
+                }
             }
         }
+        "hessian" => {
+            let packed = header.options.get("packed").is_some_and(|v| v == "true");
+            let h = if packed {
+                let values = parse_values(lines, unit_factor)?;
+                let n = packed_dimension(values.len()).map_err(|e| ParseError::new(hline, format!("{e}")))?;
+                if n == 0 || !n.is_multiple_of(3) {
+                    return Err(ParseError::new(hline, format!("expect 3Nx3N hessian, but found {n}x{n}")));
+                }
+                values
+            } else {
+                let h = parse_square_matrix(hline, lines, unit_factor)?;
+                let n = h.len();
+                if !n.is_multiple_of(3) {
+                    return Err(ParseError::new(hline, format!("expect 3Nx3N hessian, but found {n} rows")));
+                }
+                pack_symmetric_matrix(&h)
+            };
+            results.hessian = Some(h);
+        }
+        _ => {
+            debug!("keep unrecognized record: {:?}", hline.1);
+            keep_extra_section(results, header, lines);
+        }
     }
 
-    Ok(results)
+    Ok(())
 }
 
-fn parse_model_results(stream: &str) -> Result<Vec<Computed>> {
-    if stream.trim().is_empty() {
-        bail!("Attemp to parse empty string!");
+// keep a record as extra section with its raw `lines`
+fn keep_extra_section(results: &mut Computed, header: Header, lines: &[Line]) {
+    // the named unit is kept as it is, without conversion
+    let mut options: BTreeMap<_, _> = header.options.into_iter().collect();
+    if let Some(unit) = header.unit {
+        options.insert("unit".into(), unit);
     }
+    let section = ExtraSection {
+        unit_factor: header.unit_factor,
+        options,
+        lines: lines.iter().map(|l| l.1.to_string()).collect(),
+    };
+    results.extra.insert(header.name, section);
+}
 
-    // ignore commenting lines or blank lines
-    let lines: Vec<_> = stream
-        .lines()
-        .filter(|l| {
-            let l = l.trim();
-            !l.starts_with("#") && !l.is_empty()
-        })
-        .collect();
+// parse response properties: polarizability in 3x3 tensor or its packed lower
+// triangle (6 values), dipole derivatives in 3Nx3, and force constants in full
+// 3Nx3N matrix or its packed lower triangle, which are stored as Hessian. The
+// dimensions are checked against the structure if parsed. Return false if the
+// layout is not recognized, or error for invalid numbers.
+fn parse_response_record(
+    results: &mut Computed,
+    name: &str,
+    lines: &[Line],
+    unit_factor: f64,
+) -> Result<bool, ParseError> {
+    let values = parse_values(lines, unit_factor)?;
+    let n3 = results.molecule.as_ref().map(|mol| 3 * mol.natoms());
+    let m = values.len();
+    let unrecognized = |reason: String| {
+        warn!("keep @{name} record as extra data: {reason}");
+        Ok(false)
+    };
+    match name {
+        "polarizability" => {
+            let p = match m {
+                9 => [0, 1, 2].map(|i| [0, 1, 2].map(|j| values[3 * i + j])),
+                6 => {
+                    let m = unpack_symmetric_matrix(&values, 3);
+                    [0, 1, 2].map(|i| [m[i][0], m[i][1], m[i][2]])
+                }
+                _ => {
+                    let reason = format!("expect 9 values of tensor or 6 of lower triangle, but found {m}");
+                    return unrecognized(reason);
+                }
+            };
+            results.polarizability = Some(p);
+        }
+        "dipole_derivatives" => {
+            let nrows = lines.len();
+            let xyz = lines.iter().all(|l| l.1.split_whitespace().count() == 3);
+            if !xyz || n3.map_or(!nrows.is_multiple_of(3), |n| n != nrows) {
+                return unrecognized(format!("expect 3N lines of xyz components, but found {nrows} lines"));
+            }
+            let dd = values.chunks(3).map(|v| [v[0], v[1], v[2]]).collect();
+            results.dipole_derivatives = Some(dd);
+        }
+        "force_constants" => {
+            let n = (m as f64).sqrt().round() as usize;
+            let expected = |n: usize| n > 0 && n3.map_or(n.is_multiple_of(3), |n3| n3 == n);
+            let h = if n * n == m && expected(n) {
+                pack_symmetric_matrix(&values.chunks(n).map(|row| row.to_vec()).collect_vec())
+            } else if packed_dimension(m).is_ok_and(expected) {
+                values
+            } else {
+                let reason = format!("expect values of 3Nx3N matrix or its lower triangle, but found {m}");
+                return unrecognized(reason);
+            };
+            results.hessian = Some(h);
+        }
+        _ => unreachable!(),
+    }
+    Ok(true)
+}
 
-    let parts = lines[1..].split(|l| l.starts_with("@model_properties_format_version"));
+// Store forces parsed from `source` record for `state`, checking the agreement
+// with forces of the same state from the other record if any.
+fn merge_forces(
+    results: &mut Computed,
+    state: Option<usize>,
+    forces: Vec<[f64; 3]>,
+    source: ForcesSource,
+) -> Result<(), String> {
+    let (target, target_source) = match state {
+        Some(i) => {
+            let s = results.states.entry(i).or_default();
+            (&mut s.forces, &mut s.forces_source)
+        }
+        None => (&mut results.forces, &mut results.forces_source),
+    };
+    let source = match (target.as_ref(), *target_source) {
+        (Some(old), Some(s)) if s != source => {
+            let agree = old.len() == forces.len()
+                && old.iter().flatten().zip(forces.iter().flatten()).all(|(a, b)| {
+                    (a - b).abs() <= 1e-6 * a.abs().max(1.0)
+                });
+            if !agree {
+                return Err("gradient disagrees with forces".into());
+            }
+            ForcesSource::Both
+        }
+        _ => source,
+    };
+    *target = Some(forces);
+    *target_source = Some(source);
+    Ok(())
+}
 
-    let mut all_results = vec![];
-    for part in parts {
-        // collect records as header separated lines
-        // blank lines are ignored
-        // ignore empty part
-        if part.is_empty() {
-            continue;
-        } else {
-            let mp = parse_model_results_single(part)?;
-            all_results.push(mp);
+// Check the dimensions of parsed properties, and attach per-atom properties to
+// the structure if any.
+fn validate_parsed(mut results: Computed) -> Result<Computed> {
+    if let Some(i) = results.active_state {
+        ensure!(results.states.contains_key(&i), "active state {i} not found in states");
+    }
+    if let Some(h) = &results.hessian {
+        let m = packed_dimension(h.len())?;
+        ensure!(m > 0 && m.is_multiple_of(3), "hessian: expect 3Nx3N, but found {m}x{m}");
+    }
+    // the dimensions should be consistent with the structure if any
+    if let Some(mol) = &results.molecule {
+        let n = 3 * mol.natoms();
+        if let Some(dd) = &results.dipole_derivatives {
+            ensure!(
+                dd.len() == n,
+                "dipole derivatives: expect {n} rows, but found {}",
+                dd.len()
+            );
+        }
+        if let Some(h) = &results.hessian {
+            let m = packed_dimension(h.len())?;
+            ensure!(m == n, "hessian: expect {n}x{n}, but found {m}x{m}");
         }
     }
+    // attach per-atom properties to the structure
+    if let Some(mut mol) = results.molecule.take() {
+        results.attach_atomic_properties(&mut mol)?;
+        results.molecule = mol.into();
+    }
 
-    Ok(all_results)
+    Ok(results)
 }
-// 37f15603 ends here
 
-impl Computed {
-    /// Set item energy.
-    pub fn set_energy(&mut self, e: f64) {
-        self.energy = Some(e);
-    }
+// parse a floating point number in `line`
+fn parse_f64(line: Line, s: &str, unit_factor: f64) -> Result<f64, ParseError> {
+    let x = s
+        .parse::<f64>()
+        .map_err(|e| ParseError::new(line, format!("invalid number {s:?}: {e}")))?;
+    Ok(x * unit_factor)
+}
 
-    /// Set item forces.
-    pub fn set_forces(&mut self, f: Vec<[f64; 3]>) {
-        self.forces = Some(f);
+// parse lines of xyz components, such as forces
+fn parse_xyz_lines(lines: &[Line], unit_factor: f64) -> Result<Vec<[f64; 3]>, ParseError> {
+    let mut rows = vec![];
+    for &line in lines {
+        let parts: Vec<_> = line.1.split_whitespace().collect();
+        if parts.len() != 3 {
+            return Err(ParseError::new(line, "expect xyz components"));
+        }
+        let x = parse_f64(line, parts[0], unit_factor)?;
+        let y = parse_f64(line, parts[1], unit_factor)?;
+        let z = parse_f64(line, parts[2], unit_factor)?;
+        rows.push([x, y, z]);
     }
 
-    /// Set item dipole.
-    pub fn set_dipole(&mut self, d: [f64; 3]) {
-        self.dipole = Some(d);
-    }
+    Ok(rows)
+}
 
-    /// Set item Molecule.
-    pub fn set_molecule(&mut self, m: Molecule) {
-        self.molecule = Some(m);
+// parse 3x3 tensor in three lines
+fn parse_tensor(header: Line, lines: &[Line], unit_factor: f64) -> Result<[[f64; 3]; 3], ParseError> {
+    let t = parse_xyz_lines(lines, unit_factor)?;
+    if t.len() != 3 {
+        let reason = format!("expect 3 lines for 3x3 tensor, but found {}", t.len());
+        return Err(ParseError::new(header, reason));
     }
 
-    /// Set item force constants.
-    pub fn set_force_constants(&mut self, fc: Vec<[f64; 3]>) {
-        self.force_constants = Some(fc);
-    }
+    Ok([t[0], t[1], t[2]])
+}
 
-    /// Get energy component.
-    pub fn get_energy(&self) -> Option<f64> {
-        self.energy
+// parse all values in lines, regardless of the number of values per line
+fn parse_values(lines: &[Line], unit_factor: f64) -> Result<Vec<f64>, ParseError> {
+    let mut values = vec![];
+    for &line in lines {
+        for x in line.1.split_whitespace() {
+            values.push(parse_f64(line, x, unit_factor)?);
+        }
     }
 
-    /// Get dipole moment component.
-    pub fn get_dipole(&self) -> Option<[f64; 3]> {
-        self.dipole
-    }
+    Ok(values)
+}
 
-    /// Get forces component.
-    pub fn get_forces(&self) -> Option<&Vec<[f64; 3]>> {
-        self.forces.as_ref()
-    }
+// parse a square matrix in row-major order, regardless of the number of
+// values per line
+fn parse_square_matrix(header: Line, lines: &[Line], unit_factor: f64) -> Result<Vec<Vec<f64>>, ParseError> {
+    let values = parse_values(lines, unit_factor)?;
 
-    /// Get molecule structure.
-    pub fn get_molecule(&self) -> Option<&Molecule> {
-        self.molecule.as_ref()
+    let n = (values.len() as f64).sqrt().round() as usize;
+    if n == 0 || n * n != values.len() {
+        let reason = format!("expect values of a square matrix, but found {}", values.len());
+        return Err(ParseError::new(header, reason));
     }
 
-    /// Get force constants component.
-    pub fn get_force_constants(&self) -> Option<&Vec<[f64; 3]>> {
-        self.force_constants.as_ref()
+    Ok(values.chunks(n).map(|row| row.to_vec()).collect())
+}
+
+// Return the dimension n of a symmetric matrix from the size of its packed
+// lower triangle: n(n+1)/2
+fn packed_dimension(size: usize) -> Result<usize> {
+    let n = (((8 * size + 1) as f64).sqrt().round() as usize - 1) / 2;
+    ensure!(n * (n + 1) / 2 == size, "invalid size of packed lower triangle: {size}");
+    Ok(n)
+}
+
+// Pack the lower triangle of a square matrix, which is symmetrized by
+// averaging.
+fn pack_symmetric_matrix(m: &[Vec<f64>]) -> Vec<f64> {
+    let n = m.len();
+    let mut packed = Vec::with_capacity(n * (n + 1) / 2);
+    for (i, row) in m.iter().enumerate() {
+        for (j, x) in row.iter().take(i + 1).enumerate() {
+            packed.push(0.5 * (x + m[j][i]));
+        }
     }
+    packed
+}
 
-    /// Set molecule structure.
-    ///
-    /// # Parameters
-    ///
+// Unpack the lower triangle into a full symmetric matrix.
+fn unpack_symmetric_matrix(packed: &[f64], n: usize) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| {
+                    let (i, j) = if i >= j { (i, j) } else { (j, i) };
+                    packed[i * (i + 1) / 2 + j]
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Return the format version if `line` is a format version header.
+fn parse_format_version(line: &str) -> Result<Option<&'static str>> {
+    let mut parts = line.split_whitespace();
+    match parts.next() {
+        Some("@model_properties_format_version") | Some("@model_properties_file_format_version") => {
+            let v = parts.next().context("missing model properties format version")?;
+            let v = SUPPORTED_FORMAT_VERSIONS.iter().find(|&&x| x == v).with_context(|| {
+                format!("unsupported model properties format version: {v} (supported: {SUPPORTED_FORMAT_VERSIONS:?})")
+            })?;
+            Ok(Some(v))
+        }
+        _ => Ok(None),
+    }
+}
+
+// Incremental splitter of lines into entries according to format version
+// headers and `@end` delimiters.
+#[derive(Debug, Default)]
+struct EntrySplitter {
+    version: Option<&'static str>,
+    // lines with line numbers in current entry
+    part: Vec<(usize, String)>,
+    // the number of lines fed
+    nlines: usize,
+}
+
+impl EntrySplitter {
+    // Feed one line, returning an entry if it is complete.
+    fn feed(&mut self, line: &str) -> Result<Option<Vec<(usize, String)>>> {
+        self.nlines += 1;
+        let line = line.trim();
+        // ignore commenting lines or blank lines
+        if line.starts_with('#') || line.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(v) = parse_format_version(line)? {
+            let entry = if self.part.is_empty() {
+                None
+            } else {
+                ensure!(self.version != Some("0.2"), "missing @end for entry before: {line:?}");
+                Some(std::mem::take(&mut self.part))
+            };
+            self.version = Some(v);
+            Ok(entry)
+        } else if line == "@end" {
+            ensure!(self.version == Some("0.2"), "@end delimiter requires format version 0.2");
+            if self.part.is_empty() {
+                warn!("ignored empty model properties entry.");
+                Ok(None)
+            } else {
+                Ok(Some(std::mem::take(&mut self.part)))
+            }
+        } else {
+            if self.version.is_none() {
+                warn!("no format version header found, assuming version 0.1.");
+                self.version = Some("0.1");
+            }
+            self.part.push((self.nlines, line.to_string()));
+            Ok(None)
+        }
+    }
+
+    // Finish at the end of stream, returning the remaining entry if any.
+    fn finish(&mut self) -> Result<Option<Vec<(usize, String)>>> {
+        if self.part.is_empty() {
+            Ok(None)
+        } else {
+            ensure!(self.version != Some("0.2"), "incomplete model properties entry: missing @end");
+            Ok(Some(std::mem::take(&mut self.part)))
+        }
+    }
+}
+
+// parse an entry of Computed from lines collected by `EntrySplitter`
+fn parse_entry(part: &[(usize, String)]) -> Result<Computed> {
+    let lines = part.iter().map(|(i, l)| (*i, l.as_str())).collect_vec();
+    parse_model_results_single(&lines)
+}
+
+fn parse_model_results(stream: &str) -> Result<Vec<Computed>> {
+    if stream.trim().is_empty() {
+        bail!("Attemp to parse empty string!");
+    }
+
+    // adaptors could write results in JSON format
+    if is_json_stream(stream) {
+        return parse_model_results_json(stream);
+    }
+
+    let mut splitter = EntrySplitter::default();
+    let mut all_results = vec![];
+    for line in stream.lines() {
+        if let Some(part) = splitter.feed(line)? {
+            all_results.push(parse_entry(&part)?);
+        }
+    }
+    if let Some(part) = splitter.finish()? {
+        all_results.push(parse_entry(&part)?);
+    }
+
+    Ok(all_results)
+}
+// 37f15603 ends here
+
+// [[file:../models.note::4d2a8c71][4d2a8c71]]
+/// Streaming parser of model properties from a buffered reader, which yields
+/// each entry as soon as it is complete, i.e. when its `@end` delimiter (or
+/// the next version header in format version 0.1) arrives. JSON objects are
+/// also accepted, one after another such as in JSON lines, or as elements of
+/// a JSON array.
+pub struct ComputedReader<R> {
+    reader: R,
+    splitter: EntrySplitter,
+    // detected from the first non-blank character
+    format: Option<StreamFormat>,
+    done: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamFormat {
+    Text,
+    Json,
+    // with the number of elements read
+    JsonArray(usize),
+}
+
+impl<R: BufRead> ComputedReader<R> {
+    /// Construct a streaming parser reading from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            splitter: EntrySplitter::default(),
+            format: None,
+            done: false,
+        }
+    }
+
+    // Skip whitespace, and return the next character without consuming it,
+    // or None at the end of stream, with the number of line breaks skipped.
+    fn skip_whitespace(&mut self) -> Result<(Option<u8>, usize)> {
+        let mut nlines = 0;
+        loop {
+            let buf = self.reader.fill_buf().context("Failed to read model properties")?;
+            if buf.is_empty() {
+                return Ok((None, nlines));
+            }
+            let i = buf.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(buf.len());
+            nlines += buf[..i].iter().filter(|&&b| b == b'\n').count();
+            let c = buf.get(i).copied();
+            self.reader.consume(i);
+            if c.is_some() {
+                return Ok((c, nlines));
+            }
+        }
+    }
+
+    // Skip whitespace, and return the next character without consuming it.
+    fn peek_non_whitespace(&mut self) -> Result<Option<u8>> {
+        Ok(self.skip_whitespace()?.0)
+    }
+
+    // Read one JSON object from the stream, or None at the end of stream.
+    fn read_json(&mut self) -> Result<Option<Computed>> {
+        let mut stream = serde_json::Deserializer::from_reader(&mut self.reader).into_iter::<Computed>();
+        match stream.next() {
+            Some(mp) => Ok(Some(validate_parsed(mp.context("invalid JSON model properties")?)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Read the next element of JSON array after `n` elements, or None at the
+    // end of array.
+    fn read_json_element(&mut self, n: usize) -> Result<Option<Computed>> {
+        match self.peek_non_whitespace()? {
+            Some(b']') => {
+                self.reader.consume(1);
+                let trailing = self.peek_non_whitespace()?;
+                ensure!(trailing.is_none(), "trailing characters after JSON array of model properties");
+                return Ok(None);
+            }
+            Some(b',') if n > 0 => self.reader.consume(1),
+            Some(_) if n == 0 => {}
+            Some(c) => bail!("expect ',' or ']' in JSON array of model properties, but found {:?}", c as char),
+            None => bail!("incomplete JSON array of model properties"),
+        }
+        self.read_json()?.map(Some).context("incomplete JSON array of model properties")
+    }
+
+    // Read lines until an entry in text format is complete, or None at the
+    // end of stream.
+    fn read_text(&mut self) -> Result<Option<Computed>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let n = self.reader.read_line(&mut line).context("Failed to read model properties")?;
+            let part = if n == 0 {
+                self.splitter.finish()?
+            } else {
+                self.splitter.feed(&line)?
+            };
+            if let Some(part) = part {
+                return parse_entry(&part).map(Some);
+            } else if n == 0 {
+                return Ok(None);
+            }
+        }
+    }
+
+    // Read the next entry, or None at the end of stream.
+    fn read_entry(&mut self) -> Result<Option<Computed>> {
+        let format = match self.format {
+            Some(format) => format,
+            None => {
+                let (c, nlines) = self.skip_whitespace()?;
+                // keep line numbers for text format
+                self.splitter.nlines += nlines;
+                let format = match c {
+                    None => return Ok(None),
+                    Some(b'{') => StreamFormat::Json,
+                    Some(b'[') => {
+                        self.reader.consume(1);
+                        StreamFormat::JsonArray(0)
+                    }
+                    Some(_) => StreamFormat::Text,
+                };
+                self.format = Some(format);
+                format
+            }
+        };
+        match format {
+            StreamFormat::Text => self.read_text(),
+            StreamFormat::Json => self.read_json(),
+            StreamFormat::JsonArray(n) => {
+                self.format = Some(StreamFormat::JsonArray(n + 1));
+                self.read_json_element(n)
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ComputedReader<R> {
+    type Item = Result<Computed>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_entry() {
+            Ok(Some(mp)) => Some(Ok(mp)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            // stop on any error
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+// 4d2a8c71 ends here
+
+impl Computed {
+    /// Set item energy.
+    pub fn set_energy(&mut self, e: f64) {
+        self.energy = Some(e);
+    }
+
+    /// Set item forces.
+    pub fn set_forces(&mut self, f: Vec<[f64; 3]>) {
+        self.forces = Some(f);
+        self.forces_source = Some(ForcesSource::Forces);
+    }
+
+    /// Set item dipole.
+    pub fn set_dipole(&mut self, d: [f64; 3]) {
+        self.dipole = Some(d);
+    }
+
+    /// Set item Molecule.
+    pub fn set_molecule(&mut self, m: Molecule) {
+        self.molecule = Some(m);
+    }
+
+    /// Set item force constants in full 3Nx3N matrix. The same as
+    /// `set_hessian`.
+    pub fn set_force_constants(&mut self, fc: Vec<Vec<f64>>) -> Result<()> {
+        self.set_hessian(fc)
+    }
+
+    /// Set item polarizability tensor.
+    pub fn set_polarizability(&mut self, p: [[f64; 3]; 3]) {
+        self.polarizability = Some(p);
+    }
+
+    /// Set item dipole derivatives in 3Nx3.
+    pub fn set_dipole_derivatives(&mut self, dd: Vec<[f64; 3]>) {
+        self.dipole_derivatives = Some(dd);
+    }
+
+    /// Set item Hessian from a full 3Nx3N matrix, which will be symmetrized.
+    pub fn set_hessian(&mut self, h: Vec<Vec<f64>>) -> Result<()> {
+        let n = h.len();
+        ensure!(n.is_multiple_of(3), "expect 3Nx3N hessian, but found {n} rows");
+        if let Some(row) = h.iter().find(|row| row.len() != n) {
+            bail!("expect square hessian of {n} columns, but found {}", row.len());
+        }
+        if let Some(mol) = &self.molecule {
+            let m = 3 * mol.natoms();
+            ensure!(m == n, "hessian: expect {m}x{m}, but found {n}x{n}");
+        }
+        self.hessian = Some(pack_symmetric_matrix(&h));
+        Ok(())
+    }
+
+    /// Set item stress tensor.
+    pub fn set_stress(&mut self, stress: [[f64; 3]; 3]) {
+        self.stress = Some(stress);
+    }
+
+    /// Set item virial tensor.
+    pub fn set_virial(&mut self, virial: [[f64; 3]; 3]) {
+        self.virial = Some(virial);
+    }
+
+    /// Get energy component. The energy of active state is returned if set.
+    pub fn get_energy(&self) -> Option<f64> {
+        match self.active_state {
+            Some(i) => self.get_energy_of_state(i),
+            None => self.energy,
+        }
+    }
+
+    /// Get dipole moment component.
+    pub fn get_dipole(&self) -> Option<[f64; 3]> {
+        self.dipole
+    }
+
+    /// Get forces component. The forces of active state are returned if set.
+    pub fn get_forces(&self) -> Option<&Vec<[f64; 3]>> {
+        match self.active_state {
+            Some(i) => self.states.get(&i)?.forces.as_ref(),
+            None => self.forces.as_ref(),
+        }
+    }
+
+    /// Get molecule structure.
+    pub fn get_molecule(&self) -> Option<&Molecule> {
+        self.molecule.as_ref()
+    }
+
+    /// Get force constants as full 3Nx3N matrix. The same as `get_hessian`.
+    pub fn get_force_constants(&self) -> Option<Vec<Vec<f64>>> {
+        self.get_hessian()
+    }
+
+    /// Get polarizability tensor.
+    pub fn get_polarizability(&self) -> Option<[[f64; 3]; 3]> {
+        self.polarizability
+    }
+
+    /// Get dipole derivatives component.
+    pub fn get_dipole_derivatives(&self) -> Option<&Vec<[f64; 3]>> {
+        self.dipole_derivatives.as_ref()
+    }
+
+    /// Get Hessian as full 3Nx3N matrix.
+    pub fn get_hessian(&self) -> Option<Vec<Vec<f64>>> {
+        let h = self.hessian.as_ref()?;
+        let n = packed_dimension(h.len()).ok()?;
+        unpack_symmetric_matrix(h, n).into()
+    }
+
+    /// Get Hessian in packed lower triangle (row-major order).
+    pub fn get_hessian_packed(&self) -> Option<&[f64]> {
+        self.hessian.as_deref()
+    }
+
+    /// Get stress tensor.
+    pub fn get_stress(&self) -> Option<[[f64; 3]; 3]> {
+        self.stress
+    }
+
+    /// Get virial tensor.
+    pub fn get_virial(&self) -> Option<[[f64; 3]; 3]> {
+        self.virial
+    }
+
+    /// Set item partial charges of atoms.
+    pub fn set_charges(&mut self, charges: Vec<f64>) {
+        self.charges = Some(charges);
+    }
+
+    /// Set item spin populations of atoms.
+    pub fn set_spins(&mut self, spins: Vec<f64>) {
+        self.spins = Some(spins);
+    }
+
+    /// Set item energy contributions of atoms.
+    pub fn set_atomic_energies(&mut self, energies: Vec<f64>) {
+        self.atomic_energies = Some(energies);
+    }
+
+    /// Get partial charges of atoms.
+    pub fn get_charges(&self) -> Option<&[f64]> {
+        self.charges.as_deref()
+    }
+
+    /// Get spin populations of atoms.
+    pub fn get_spins(&self) -> Option<&[f64]> {
+        self.spins.as_deref()
+    }
+
+    /// Get energy contributions of atoms.
+    pub fn get_atomic_energies(&self) -> Option<&[f64]> {
+        self.atomic_energies.as_deref()
+    }
+
+    /// Set unrecognized section `name` for passing program-specific data.
+    pub fn set_extra<S: Into<String>>(&mut self, name: S, section: ExtraSection) {
+        self.extra.insert(name.into(), section);
+    }
+
+    /// Get unrecognized section `name` if any.
+    pub fn get_extra(&self, name: &str) -> Option<&ExtraSection> {
+        self.extra.get(name)
+    }
+
+    /// Return an iterator over all unrecognized sections in name order.
+    pub fn extra_sections(&self) -> impl Iterator<Item = (&str, &ExtraSection)> {
+        self.extra.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Set energy of electronic state `i`.
+    pub fn set_energy_of_state(&mut self, i: usize, e: f64) {
+        self.states.entry(i).or_default().energy = Some(e);
+    }
+
+    /// Set forces from energy gradient, which is converted to forces by
+    /// flipping the sign.
+    pub fn set_gradient(&mut self, g: Vec<[f64; 3]>) {
+        self.forces = Some(g.into_iter().map(|v| v.map(|x| -x)).collect());
+        self.forces_source = Some(ForcesSource::Gradient);
+    }
+
+    /// Return the record from which forces were obtained, if known. The
+    /// source of active state is returned if set.
+    pub fn get_forces_source(&self) -> Option<ForcesSource> {
+        match self.active_state {
+            Some(i) => self.get_forces_source_of_state(i),
+            None => self.forces_source,
+        }
+    }
+
+    /// Set forces of electronic state `i`.
+    pub fn set_forces_of_state(&mut self, i: usize, f: Vec<[f64; 3]>) {
+        let s = self.states.entry(i).or_default();
+        s.forces = Some(f);
+        s.forces_source = Some(ForcesSource::Forces);
+    }
+
+    /// Return the record from which forces of electronic state `i` were
+    /// obtained, if known.
+    pub fn get_forces_source_of_state(&self, i: usize) -> Option<ForcesSource> {
+        self.states.get(&i)?.forces_source
+    }
+
+    /// Get energy of electronic state `i`.
+    pub fn get_energy_of_state(&self, i: usize) -> Option<f64> {
+        self.states.get(&i)?.energy
+    }
+
+    /// Get forces of electronic state `i`.
+    pub fn get_forces_of_state(&self, i: usize) -> Option<&[[f64; 3]]> {
+        self.states.get(&i)?.forces.as_deref()
+    }
+
+    /// Return indices of all electronic states in ascending order.
+    pub fn states(&self) -> impl Iterator<Item = usize> + '_ {
+        self.states.keys().copied()
+    }
+
+    /// Set electronic state `i` as the active state (root), whose energy and
+    /// forces will be returned by `get_energy` and `get_forces`. Set to
+    /// `None` for unindexed energy and forces.
+    pub fn set_active_state(&mut self, i: Option<usize>) -> Result<()> {
+        if let Some(i) = i {
+            ensure!(self.states.contains_key(&i), "no such state: {i}");
+        }
+        self.active_state = i;
+        Ok(())
+    }
+
+    /// Return the index of active state if any.
+    pub fn get_active_state(&self) -> Option<usize> {
+        self.active_state
+    }
+
+    /// Set energy component `name`, such as "dispersion".
+    pub fn set_energy_component<S: Into<String>>(&mut self, name: S, e: f64) {
+        self.energy_components.insert(name.into(), e);
+    }
+
+    /// Get energy component `name` if any.
+    pub fn get_energy_component(&self, name: &str) -> Option<f64> {
+        self.energy_components.get(name).copied()
+    }
+
+    /// Return an iterator over all energy components in name order.
+    pub fn energy_components(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.energy_components.iter().map(|(k, &v)| (k.as_str(), v))
+    }
+
+    /// Set energies of molecular orbitals in eV, with optional occupation
+    /// numbers.
+    pub fn set_orbital_energies(&mut self, energies: Vec<f64>, occupations: Option<Vec<f64>>) -> Result<()> {
+        if let Some(occ) = &occupations {
+            let (n, m) = (energies.len(), occ.len());
+            ensure!(n == m, "expect {n} occupation numbers, but found {m}");
+        }
+        self.orbital_energies = Some(energies);
+        self.orbital_occupations = occupations;
+        Ok(())
+    }
+
+    /// Get energies of molecular orbitals in eV.
+    pub fn get_orbital_energies(&self) -> Option<&[f64]> {
+        self.orbital_energies.as_deref()
+    }
+
+    /// Get occupation numbers of molecular orbitals.
+    pub fn get_orbital_occupations(&self) -> Option<&[f64]> {
+        self.orbital_occupations.as_deref()
+    }
+
+    /// Set whether SCF iterations converged.
+    pub fn set_scf_converged(&mut self, converged: bool) {
+        self.scf_converged = Some(converged);
+    }
+
+    /// Return whether SCF iterations converged, if reported.
+    pub fn get_scf_converged(&self) -> Option<bool> {
+        self.scf_converged
+    }
+
+    /// Set the number of SCF iterations.
+    pub fn set_scf_iterations(&mut self, n: usize) {
+        self.scf_iterations = Some(n);
+    }
+
+    /// Get the number of SCF iterations, if reported.
+    pub fn get_scf_iterations(&self) -> Option<usize> {
+        self.scf_iterations
+    }
+
+    /// Return HOMO-LUMO gap in eV from orbital energies and occupations.
+    /// Orbitals with occupation number no less than 0.5 are regarded as
+    /// occupied. Return None if no occupied or virtual orbitals available.
+    pub fn homo_lumo_gap(&self) -> Option<f64> {
+        let energies = self.orbital_energies.as_ref()?;
+        let occupations = self.orbital_occupations.as_ref()?;
+        let orbitals = energies.iter().zip(occupations);
+        let (occupied, virtual_): (Vec<_>, Vec<_>) = orbitals.partition(|(_, &occ)| occ >= 0.5);
+        let homo = occupied.iter().map(|(&e, _)| e).max_by(|a, b| a.total_cmp(b))?;
+        let lumo = virtual_.iter().map(|(&e, _)| e).min_by(|a, b| a.total_cmp(b))?;
+        Some(lumo - homo)
+    }
+
+    /// Set metadata `key` to `value`, such as "program" or "version".
+    pub fn set_meta<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        self.meta.insert(key.into(), value.into());
+    }
+
+    /// Get metadata for `key` if any.
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        self.meta.get(key).map(|v| v.as_str())
+    }
+
+    /// Return an iterator over all metadata in key order.
+    pub fn meta(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.meta.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Check if the sum of energy components agrees with the total energy
+    /// within `tolerance` in eV.
+    pub fn check_energy_components(&self, tolerance: f64) -> Result<()> {
+        ensure!(!self.energy_components.is_empty(), "no energy components");
+        let total = self.get_energy().ok_or_else(|| format_err!("no total energy"))?;
+        let sum: f64 = self.energy_components.values().sum();
+        let diff = (sum - total).abs();
+        ensure!(
+            diff <= tolerance,
+            "sum of energy components {sum} differs from total energy {total} by {diff}"
+        );
+        Ok(())
+    }
+
+    /// Attach per-atom properties (charges, spins and atomic energies) to
+    /// atoms in `mol`, which are accessible from atom properties with keys
+    /// of "charge", "spin" and "atomic_energy" respectively. Partial charges
+    /// are also set using `Atom::set_partial_charge`.
+    pub fn attach_atomic_properties(&self, mol: &mut Molecule) -> Result<()> {
+        let natoms = mol.natoms();
+        let sns = mol.serial_numbers().collect_vec();
+        for (key, values) in [
+            ("charge", &self.charges),
+            ("spin", &self.spins),
+            ("atomic_energy", &self.atomic_energies),
+        ] {
+            if let Some(values) = values {
+                let n = values.len();
+                ensure!(n == natoms, "{key}: expect {natoms} values, but found {n}");
+                for (&sn, &x) in sns.iter().zip(values) {
+                    let atom = mol.get_atom_mut(sn).expect("atom from serial number");
+                    if key == "charge" {
+                        atom.set_partial_charge(x);
+                    }
+                    atom.properties.store(key, x)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check computed properties against the input molecule `mol`, returning
+    /// a descriptive error on the first inconsistency found:
+    ///
+    /// * the number of forces (and other per-atom properties) should be equal
+    ///   to the number of atoms
+    /// * all values should be finite (no NaN or inf)
+    /// * the returned structure, if any, should have the same elements in
+    ///   the same order, and have lattice only if `mol` is periodic. Missing
+    ///   lattice is accepted, as adaptors could write structure without cell.
+    pub fn validate_against(&self, mol: &Molecule) -> Result<()> {
+        let natoms = mol.natoms();
+
+        // number of atoms
+        if let Some(f) = &self.forces {
+            ensure!(f.len() == natoms, "expect forces of {natoms} atoms, but found {}", f.len());
+        }
+        for (i, state) in &self.states {
+            if let Some(f) = &state.forces {
+                let n = f.len();
+                ensure!(n == natoms, "state {i}: expect forces of {natoms} atoms, but found {n}");
+            }
+            if let Some(e) = state.energy.filter(|e| !e.is_finite()) {
+                bail!("found invalid value {e} in energy of state {i}");
+            }
+            if state.forces.iter().flatten().flatten().any(|x| !x.is_finite()) {
+                bail!("found invalid value in forces of state {i}");
+            }
+        }
+        for (key, values) in [
+            ("charges", &self.charges),
+            ("spins", &self.spins),
+            ("atomic_energies", &self.atomic_energies),
+        ] {
+            if let Some(n) = values.as_ref().map(|v| v.len()) {
+                ensure!(n == natoms, "expect {key} of {natoms} atoms, but found {n}");
+            }
+        }
+        let n = 3 * natoms;
+        if let Some(h) = &self.hessian {
+            ensure!(h.len() == n * (n + 1) / 2, "expect {n}x{n} hessian, but found {} values", h.len());
+        }
+        if let Some(dd) = &self.dipole_derivatives {
+            ensure!(dd.len() == n, "expect {n}x3 dipole derivatives, but found {} rows", dd.len());
+        }
+
+        // NaN or inf values
+        let flatten = |t: &Option<[[f64; 3]; 3]>| t.iter().flatten().flatten().copied().collect_vec();
+        for (key, values) in [
+            ("energy", self.energy.iter().copied().collect_vec()),
+            ("forces", self.forces.iter().flatten().flatten().copied().collect_vec()),
+            ("dipole", self.dipole.iter().flatten().copied().collect_vec()),
+            ("stress", flatten(&self.stress)),
+            ("virial", flatten(&self.virial)),
+            ("polarizability", flatten(&self.polarizability)),
+            ("hessian", self.hessian.iter().flatten().copied().collect_vec()),
+            ("dipole_derivatives", self.dipole_derivatives.iter().flatten().flatten().copied().collect_vec()),
+            ("charges", self.charges.iter().flatten().copied().collect_vec()),
+            ("spins", self.spins.iter().flatten().copied().collect_vec()),
+            ("atomic_energies", self.atomic_energies.iter().flatten().copied().collect_vec()),
+        ] {
+            if let Some(i) = values.iter().position(|x| !x.is_finite()) {
+                bail!("found invalid value {} in {key} at position {i}", values[i]);
+            }
+        }
+
+        // the returned structure
+        if let Some(pmol) = &self.molecule {
+            let m = pmol.natoms();
+            ensure!(m == natoms, "expect structure of {natoms} atoms, but found {m}");
+            for (i, (a, b)) in mol.symbols().zip(pmol.symbols()).enumerate() {
+                ensure!(a == b, "element mismatch for atom {}: expect {a}, but found {b}", i + 1);
+            }
+            if pmol.positions().flatten().any(|x| !x.is_finite()) {
+                bail!("found invalid value in atom positions of structure");
+            }
+            if pmol.get_lattice().is_some() && mol.get_lattice().is_none() {
+                bail!("expect molecular structure, but found lattice");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set molecule structure.
+    ///
+    /// # Parameters
+    ///
     /// * atoms: a list of symbol and position pairs
     ///
     /// * cell: three Lattice vectors array
@@ -325,7 +1979,7 @@ impl Computed {
 
 // [[file:../models.note::6d51755f][6d51755f]]
 #[test]
-fn test_model_parse_results() {
+fn test_model_parse_results() -> Result<()> {
     use vecfx::approx::*;
 
     use serde_json;
@@ -333,22 +1987,666 @@ fn test_model_parse_results() {
     let txt = gchemol::io::read_file("tests/files/sample.txt").unwrap();
     let r: Computed = txt.parse().expect("model results");
 
+    // polarizability in packed lower triangle
+    let p = r.get_polarizability().expect("model result: polarizability");
+    assert_relative_eq!(p[0][0], -0.54370868654757E-04, epsilon = 1e-12);
+    assert_relative_eq!(p[0][1], p[1][0], epsilon = 1e-12);
+    assert_relative_eq!(p[2][2], -0.47971139002212E-04, epsilon = 1e-12);
+    // inconsistent with the structure, kept as extra data
+    assert!(r.get_dipole_derivatives().is_none());
+    assert_eq!(r.get_extra("dipole_derivatives").unwrap().as_xyz()?.len(), 3);
+    assert!(r.get_force_constants().is_none());
+    assert_eq!(r.get_extra("force_constants").unwrap().as_vector()?.len(), 57);
+
     // serializing
     let serialized = serde_json::to_string(&r).unwrap();
     // and deserializing
-    let _: Computed = serde_json::from_str(&serialized).unwrap();
+    let r_: Computed = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(r_.polarizability, r.polarizability);
+    assert_eq!(r_.extra, r.extra);
+    let (mol, mol_) = (r.get_molecule().unwrap(), r_.get_molecule().expect("structure in json"));
+    assert_eq!(mol_.symbols().collect_vec(), mol.symbols().collect_vec());
+    assert_eq!(mol_.positions().collect_vec(), mol.positions().collect_vec());
 
     // reformat
     let txt = format!("{}", r);
 
     // parse again
     let r: Computed = txt.parse().expect("model results");
+    assert_eq!(r.get_extra("force_constants").unwrap().as_vector()?.len(), 57);
 
     assert!(&r.molecule.is_some());
-    let ref mol = r.molecule.unwrap();
+    let mol = &r.molecule.unwrap();
     assert_eq!(3, mol.natoms());
     let e = r.energy.expect("model result: energy");
     assert_relative_eq!(-0.329336, e, epsilon = 1e-4);
+
+    // force constants in packed lower triangle, and dipole derivatives in 3Nx3
+    let txt = "@structure
+O 0.0 0.0 0.0
+H 0.0 0.0 0.96
+H 0.0 0.93 -0.24
+@force_constants
+ 1.0  2.0  3.0  4.0  5.0  6.0  7.0  8.0  9.0
+10.0 11.0 12.0 13.0 14.0 15.0 16.0 17.0 18.0
+19.0 20.0 21.0 22.0 23.0 24.0 25.0 26.0 27.0
+28.0 29.0 30.0 31.0 32.0 33.0 34.0 35.0 36.0
+37.0 38.0 39.0 40.0 41.0 42.0 43.0 44.0 45.0
+@dipole_derivatives
+0.1 0.0 0.0
+0.0 0.1 0.0
+0.0 0.0 0.1
+0.1 0.0 0.0
+0.0 0.1 0.0
+0.0 0.0 0.1
+0.1 0.0 0.0
+0.0 0.1 0.0
+0.0 0.0 0.1
+";
+    let r: Computed = txt.parse()?;
+    let fc = r.get_force_constants().expect("model result: force constants");
+    assert_eq!(fc[8][..2], [37.0, 38.0]);
+    assert_eq!(fc[0][8], 37.0);
+    // stored as Hessian
+    assert_eq!(r.get_hessian_packed().unwrap().len(), 45);
+    assert_eq!(r.get_dipole_derivatives().unwrap().len(), 9);
+
+    // invalid numbers are not kept
+    let txt = "@model_properties_format_version 0.2\n@polarizability\n1 2 x\n@end\n";
+    let e = txt.parse::<Computed>().unwrap_err();
+    let e = e.downcast_ref::<ParseError>().expect("parse error");
+    assert_eq!((e.section.as_str(), e.line), ("polarizability", 3));
+
+    // unrecognized layout kept as extra data without unit conversion
+    let txt = "@model_properties_format_version 0.2\n@force_constants unit=hartree/bohr^2\n1.0 2.0\n@end\n";
+    let r: Computed = txt.parse()?;
+    let fc = r.get_extra("force_constants").unwrap();
+    assert_eq!(fc.get_option("unit"), Some("hartree/bohr^2"));
+    assert_eq!(fc.as_vector()?, [1.0, 2.0]);
+    let r_: Computed = r.to_string().parse()?;
+    assert_eq!(r_.extra, r.extra);
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_hessian() -> Result<()> {
+    let txt = "@model_properties_format_version 0.1
+@hessian
+1.0 0.1 0.0 0.0 0.0 0.0
+0.3 2.0 0.0 0.0 0.0 0.0
+0.0 0.0 3.0 0.0 0.0 0.0
+0.0 0.0 0.0 4.0 0.0 0.0
+0.0 0.0 0.0 0.0 5.0 0.0
+0.0 0.0 0.0 0.0 0.0 6.0
+";
+    let r: Computed = txt.parse()?;
+    let h = r.get_hessian().unwrap();
+    assert_eq!(h.len(), 6);
+    // symmetrized
+    assert_eq!(h[0][1], 0.2);
+    assert_eq!(h[1][0], 0.2);
+    assert_eq!(r.get_hessian_packed().unwrap().len(), 21);
+
+    // round trip in packed format
+    let r_: Computed = r.to_string().parse()?;
+    assert_eq!(r_.hessian, r.hessian);
+    let r_: Computed = serde_json::from_str(&serde_json::to_string(&r)?)?;
+    assert_eq!(r_.hessian, r.hessian);
+
+    // inconsistent with the structure
+    let txt = format!("{txt}@structure\nO 0.0 0.0 0.0\nH 0.0 0.0 0.96\nH 0.0 0.93 -0.24\n");
+    assert!(txt.parse::<Computed>().is_err());
+
+    // empty or invalid size of packed lower triangle
+    let txt = "@model_properties_format_version 0.2\n@hessian packed=true\n@end\n";
+    assert!(txt.parse::<Computed>().is_err());
+    assert!(r#"{"energy": -1.0, "hessian": [1.0, 2.0]}"#.parse::<Computed>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_atomic_properties() -> Result<()> {
+    let txt = "@model_properties_format_version 0.1
+@structure
+O 0.0 0.0 0.0
+H 0.0 0.0 0.96
+H 0.0 0.93 -0.24
+@charges
+-0.8
+0.4
+0.4
+@atomic_energies
+-1.0 -0.5 -0.5
+";
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.get_charges().unwrap(), &[-0.8, 0.4, 0.4]);
+    assert_eq!(r.get_atomic_energies().unwrap().len(), 3);
+    assert!(r.get_spins().is_none());
+    let mol = r.get_molecule().unwrap();
+    let atom = mol.get_atom(1).unwrap();
+    assert_eq!(atom.properties.load::<f64>("charge")?, -0.8);
+    assert_eq!(atom.properties.load::<f64>("atomic_energy")?, -1.0);
+
+    // round trip
+    let r_: Computed = r.to_string().parse()?;
+    assert_eq!(r_.charges, r.charges);
+    assert_eq!(r_.atomic_energies, r.atomic_energies);
+
+    // inconsistent with the structure
+    let txt = txt.replace("-1.0 -0.5 -0.5", "-1.0 -0.5");
+    assert!(txt.parse::<Computed>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_extra_sections() -> Result<()> {
+    let txt = "@model_properties_format_version 0.1
+@energy
+-1.0
+@band_gap unit_factor=2.0 spin=up
+1.5
+@scf_cycles
+12
+@atomic_polarizations
+0.1 0.2 0.3
+0.4 0.5 0.6
+";
+    let r: Computed = txt.parse()?;
+    let gap = r.get_extra("band_gap").unwrap();
+    assert_eq!(gap.as_scalar()?, 3.0);
+    assert_eq!(gap.get_option("spin"), Some("up"));
+    assert_eq!(r.get_extra("scf_cycles").unwrap().as_scalar()?, 12.0);
+    let p = r.get_extra("atomic_polarizations").unwrap().as_xyz()?;
+    assert_eq!(p[1], [0.4, 0.5, 0.6]);
+    assert!(r.get_extra("scf_cycles").unwrap().as_xyz().is_err());
+
+    // round trip
+    let r_: Computed = r.to_string().parse()?;
+    assert_eq!(r_.extra, r.extra);
+    let r_: Computed = serde_json::from_str(&serde_json::to_string(&r)?)?;
+    assert_eq!(r_.extra, r.extra);
+
+    let mut r = Computed::default();
+    r.set_extra("homo", ExtraSection::from_scalar(-5.5));
+    let r_: Computed = r.to_string().parse()?;
+    assert_eq!(r_.get_extra("homo").unwrap().as_scalar()?, -5.5);
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_format_version() -> Result<()> {
+    // version 0.1: entries separated by version headers in either spelling
+    let txt = "@model_properties_format_version 0.1
+@energy
+-1.0
+@model_properties_file_format_version 0.1
+@energy
+-2.0
+";
+    let all = Computed::parse_all(txt)?;
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[1].get_energy(), Some(-2.0));
+
+    // version 0.2: entries terminated with @end
+    let txt = "@model_properties_format_version 0.2
+@energy
+-1.0
+@end
+@energy
+-2.0
+@end
+";
+    let all = Computed::parse_all(txt)?;
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[1].get_energy(), Some(-2.0));
+
+    // missing @end
+    let txt = txt.trim_end().trim_end_matches("@end");
+    assert!(Computed::parse_all(txt).is_err());
+
+    // unknown version
+    let txt = "@model_properties_format_version 9.9\n@energy\n-1.0\n";
+    assert!(Computed::parse_all(txt).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_units() -> Result<()> {
+    use vecfx::approx::*;
+
+    let txt = "@model_properties_format_version 0.2
+@energy unit=hartree
+-1.0
+@forces unit=hartree/bohr
+0.1 0.0 0.0
+@band_gap unit=ev
+1.5
+@end
+";
+    let r: Computed = txt.parse()?;
+    assert_relative_eq!(r.get_energy().unwrap(), -27.211386245988, epsilon = 1e-8);
+    assert_relative_eq!(r.get_forces().unwrap()[0][0], 5.142206747632589, epsilon = 1e-8);
+    assert_eq!(r.get_extra("band_gap").unwrap().get_option("unit"), Some("ev"));
+
+    // unknown unit
+    let txt = txt.replace("unit=hartree/bohr", "unit=hartree/furlong");
+    assert!(txt.parse::<Computed>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_json() -> Result<()> {
+    let txt = r#"{"energy": -1.0, "forces": [[0.1, 0.0, 0.0], [-0.1, 0.0, 0.0], [0.0, 0.0, 0.0]],
+"structure": {"symbols": ["O", "H", "H"], "positions": [[0.0, 0.0, 0.0], [0.0, 0.0, 0.96], [0.0, 0.93, -0.24]]},
+"charges": [-0.8, 0.4, 0.4]}"#;
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.get_energy(), Some(-1.0));
+    let mol = r.get_molecule().unwrap();
+    assert_eq!(mol.natoms(), 3);
+    assert_eq!(mol.symbols().collect_vec(), ["O", "H", "H"]);
+    assert_eq!(mol.get_atom(1).unwrap().properties.load::<f64>("charge")?, -0.8);
+
+    // JSON lines for bunch mode
+    let txt = "{\"energy\": -1.0}\n\n{\"energy\": -2.0}\n";
+    let all = Computed::parse_all(txt)?;
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[1].get_energy(), Some(-2.0));
+
+    // JSON array
+    let all = Computed::parse_all("[{\"energy\": -1.0}, {\"energy\": -2.0}]")?;
+    assert_eq!(all.len(), 2);
+
+    // error in multi-line object
+    let e = "{\n\"energy\": -1.0,\n\"forces\": 1.0\n}".parse::<Computed>().unwrap_err();
+    assert!(format!("{e:#}").contains("line 3"), "{e:#}");
+
+    // inconsistent structure
+    let txt = r#"{"structure": {"symbols": ["H"], "positions": []}}"#;
+    assert!(txt.parse::<Computed>().is_err());
+
+    // versioned schema with lattice
+    let mut r = Computed::default();
+    let cell = [[5.0, 0.0, 0.0], [0.0, 5.0, 0.0], [0.0, 0.0, 5.0]];
+    r.set_structure(vec![("Si", [0.0, 0.0, 0.0])], Some(cell), false);
+    let json = serde_json::to_value(&r)?;
+    assert_eq!(json["schema_version"], JSON_SCHEMA_VERSION);
+    assert_eq!(json["structure"]["lattice"][1][1], 5.0);
+    let r_: Computed = serde_json::from_value(json)?;
+    assert!(r_.get_molecule().unwrap().get_lattice().is_some());
+    assert!(r#"{"schema_version": "2.0", "energy": -1.0}"#.parse::<Computed>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_streaming() -> Result<()> {
+    use std::io::Cursor;
+
+    let txt = "@model_properties_format_version 0.2
+@energy
+-1.0
+@end
+# comment
+@model_properties_format_version 0.2
+@energy
+-2.0
+@end
+";
+    let all: Vec<_> = ComputedReader::new(Cursor::new(txt)).collect::<Result<_>>()?;
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[1].get_energy(), Some(-2.0));
+
+    // version 0.1: the last entry is complete at the end of stream
+    let txt = gchemol::io::read_file("tests/files/sample.txt")?;
+    let all: Vec<_> = ComputedReader::new(Cursor::new(txt)).collect::<Result<_>>()?;
+    assert_eq!(all.len(), 1);
+
+    // JSON lines
+    let txt = "{\"energy\": -1.0}\n{\"energy\":\n -2.0}\n";
+    let all: Vec<_> = ComputedReader::new(Cursor::new(txt)).collect::<Result<_>>()?;
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[1].get_energy(), Some(-2.0));
+
+    // JSON array
+    let txt = "[{\"energy\": -1.0},\n {\"energy\": -2.0}]\n";
+    let all: Vec<_> = ComputedReader::new(Cursor::new(txt)).collect::<Result<_>>()?;
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[1].get_energy(), Some(-2.0));
+
+    // entries of JSON array are yielded before the array is complete
+    let txt = "[{\"energy\": -1.0},\n {\"energy\": -2.0},\n {\"energy\": ";
+    let mut reader = ComputedReader::new(Cursor::new(txt));
+    assert_eq!(reader.next().unwrap()?.get_energy(), Some(-1.0));
+    assert_eq!(reader.next().unwrap()?.get_energy(), Some(-2.0));
+    assert!(reader.next().unwrap().is_err());
+    let all: Vec<_> = ComputedReader::new(Cursor::new(" [ ]\n")).collect::<Result<_>>()?;
+    assert!(all.is_empty());
+    assert!(ComputedReader::new(Cursor::new("[{\"energy\": -1.0} {}]")).any(|x| x.is_err()));
+
+    // line numbers are kept after leading blank lines
+    let txt = "\n\n@model_properties_format_version 0.2\n@energy\nx\n@end\n";
+    let e = ComputedReader::new(Cursor::new(txt)).next().unwrap().unwrap_err();
+    assert_eq!(e.downcast_ref::<ParseError>().expect("parse error").line, 5);
+
+    // stop at the first error
+    let txt = "@model_properties_format_version 0.2\n@energy\n-1.0\n@end\n@energy\n-2.0\n";
+    let mut reader = ComputedReader::new(Cursor::new(txt));
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_err());
+    assert!(reader.next().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_errors() {
+    let parse_error = |txt: &str| -> ParseError {
+        let e = txt.parse::<Computed>().unwrap_err();
+        e.downcast_ref::<ParseError>().expect("parse error").clone()
+    };
+
+    let e = parse_error("@model_properties_format_version 0.2\n@energy\n-1.0\n-2.0\n@end\n");
+    assert_eq!(e.section, "energy");
+    assert_eq!(e.line, 2);
+
+    // missing components of dipole moment
+    let e = parse_error("@model_properties_format_version 0.2\n\n@dipole\n0.1 0.2\n@end\n");
+    assert_eq!(e.section, "dipole");
+    assert_eq!(e.line, 4);
+    assert_eq!(e.text, "0.1 0.2");
+
+    // invalid number
+    let e = parse_error("@model_properties_format_version 0.2\n@forces\n0.1 0.2 0.3\n0.1 x 0.3\n@end\n");
+    assert_eq!(e.section, "forces");
+    assert_eq!(e.line, 4);
+
+    // invalid unit factor
+    let e = parse_error("@model_properties_format_version 0.2\n@forces unit_factor=x\n0.1 0.2 0.3\n@end\n");
+    assert_eq!(e.section, "forces");
+    assert_eq!(e.line, 2);
+
+    // empty section name and empty matrix
+    assert!("@model_properties_format_version 0.2\n@\n1.0\n@end\n".parse::<Computed>().is_err());
+    assert!("@model_properties_format_version 0.2\n@hessian\n@energy\n1.0\n@end\n".parse::<Computed>().is_err());
+}
+
+#[test]
+fn test_model_parse_states() -> Result<()> {
+    let txt = "@model_properties_format_version 0.2
+@energy
+-1.0
+@energy state=1
+-1.0
+@energy state=2
+-0.5
+@forces state=2
+0.1 0.2 0.3
+@active_state
+2
+@end
+";
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.states().collect_vec(), [1, 2]);
+    assert_eq!(r.get_active_state(), Some(2));
+    assert_eq!(r.get_energy(), Some(-0.5));
+    assert_eq!(r.get_forces().map(|f| f.len()), Some(1));
+    assert_eq!(r.get_energy_of_state(1), Some(-1.0));
+    assert_eq!(r.get_forces_of_state(1), None);
+
+    // round trip
+    let mut r: Computed = r.to_string().parse()?;
+    assert_eq!(r.get_energy(), Some(-0.5));
+    r.set_active_state(None)?;
+    assert_eq!(r.get_energy(), Some(-1.0));
+    assert!(r.set_active_state(Some(3)).is_err());
+
+    // invalid states
+    let txt = "@model_properties_format_version 0.2\n@energy state=x\n-1.0\n@end\n";
+    assert!(txt.parse::<Computed>().is_err());
+    let txt = "@model_properties_format_version 0.2\n@energy state=1\n-1.0\n@active_state\n2\n@end\n";
+    assert!(txt.parse::<Computed>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_gradient() -> Result<()> {
+    let txt = "@model_properties_format_version 0.2
+@gradient unit=hartree/bohr
+0.1 0.0 -0.2
+@end
+";
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.get_forces_source(), Some(ForcesSource::Gradient));
+    let f = r.get_forces().unwrap();
+    assert!(f[0][0] < 0.0 && f[0][2] > 0.0);
+
+    // consistent forces and gradient
+    let txt = "@model_properties_format_version 0.2
+@forces
+-0.1 0.0 0.2
+@gradient
+0.1 0.0 -0.2
+@end
+";
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.get_forces_source(), Some(ForcesSource::Both));
+    assert_eq!(r.get_forces().unwrap()[0], [-0.1, 0.0, 0.2]);
+
+    // inconsistent
+    let txt = txt.replace("-0.2", "0.2");
+    let e = txt.parse::<Computed>().unwrap_err();
+    let e = e.downcast_ref::<ParseError>().expect("parse error");
+    assert_eq!(e.section, "gradient");
+
+    // forces and gradient of different states are not compared
+    let txt = "@model_properties_format_version 0.2
+@forces state=1
+-0.1 0.0 0.2
+@gradient state=2
+0.3 0.0 -0.4
+@end
+";
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.get_forces_source_of_state(1), Some(ForcesSource::Forces));
+    assert_eq!(r.get_forces_source_of_state(2), Some(ForcesSource::Gradient));
+    assert_eq!(r.get_forces_of_state(2).unwrap()[0], [-0.3, 0.0, 0.4]);
+
+    // neither are unindexed ones and those of a state
+    let txt = "@model_properties_format_version 0.2
+@gradient
+0.1 0.0 -0.2
+@forces state=1
+0.3 0.0 -0.4
+@end
+";
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.get_forces_source(), Some(ForcesSource::Gradient));
+    assert_eq!(r.get_forces_source_of_state(1), Some(ForcesSource::Forces));
+
+    // sources are kept in text round trip
+    let mut r = r;
+    r.set_forces_of_state(2, vec![[0.1, 0.0, -0.2]]);
+    let r_: Computed = r.to_string().parse()?;
+    assert_eq!(r_.get_forces_source(), Some(ForcesSource::Gradient));
+    assert_eq!(r_.get_forces(), r.get_forces());
+    assert_eq!(r_.get_forces_source_of_state(1), Some(ForcesSource::Forces));
+    assert_eq!(r_.get_forces_source_of_state(2), Some(ForcesSource::Forces));
+    let txt = "@model_properties_format_version 0.2\n@forces\n-0.1 0.0 0.2\n@gradient\n0.1 0.0 -0.2\n@end\n";
+    let r: Computed = txt.parse()?;
+    let r_: Computed = r.to_string().parse()?;
+    assert_eq!(r_.get_forces_source(), Some(ForcesSource::Both));
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_lattice() -> Result<()> {
+    use vecfx::approx::*;
+
+    let txt = "@model_properties_format_version 0.2
+@structure fractional=true
+Si 0.0 0.0 0.0
+Si 0.25 0.25 0.25
+Si 0.5 0.5 0.0
+@lattice unit=bohr
+10.0 0.0 0.0
+0.0 10.0 0.0
+0.0 0.0 10.0
+@energy
+-1.0
+@end
+";
+    let r: Computed = txt.parse()?;
+    let mol = r.get_molecule().expect("structure");
+    let lat = mol.get_lattice().expect("lattice");
+    let a: f64 = 10.0 * 0.529177210903;
+    assert_relative_eq!(lat.volume(), a.powi(3), epsilon = 1e-6);
+    let p = mol.positions().nth(1).unwrap();
+    assert_relative_eq!(p[0], 0.25 * a, epsilon = 1e-6);
+
+    // round trip
+    let r_: Computed = r.to_string().parse()?;
+    let mol_ = r_.get_molecule().expect("structure");
+    assert_relative_eq!(mol_.get_lattice().expect("lattice").volume(), lat.volume(), epsilon = 1e-6);
+    assert_relative_eq!(mol_.positions().nth(1).unwrap()[0], p[0], epsilon = 1e-8);
+
+    // fractional coordinates require lattice
+    let txt = "@model_properties_format_version 0.2\n@structure fractional=true\nSi 0.0 0.0 0.0\n@end\n";
+    assert!(txt.parse::<Computed>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_energy_components() -> Result<()> {
+    use vecfx::approx::*;
+
+    let txt = "@model_properties_format_version 0.2
+@energy unit=hartree
+-1.0
+@energy_components unit=hartree
+electrostatic -0.8
+dispersion -0.05
+repulsion -0.15
+@end
+";
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.energy_components().count(), 3);
+    let e = r.get_energy_component("dispersion").unwrap();
+    assert_relative_eq!(e, -0.05 * 27.211386245988, epsilon = 1e-8);
+    r.check_energy_components(1e-6)?;
+
+    // round trip with text and json
+    let r_: Computed = r.to_string().parse()?;
+    assert_relative_eq!(r_.get_energy_component("dispersion").unwrap(), e, epsilon = 1e-8);
+    let r_: Computed = serde_json::from_str(&serde_json::to_string(&r)?)?;
+    assert_eq!(r_.get_energy_component("dispersion"), Some(e));
+
+    let mut r = r;
+    r.set_energy_component("solvation", -0.1);
+    assert!(r.check_energy_components(1e-6).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_electronic_structure() -> Result<()> {
+    use vecfx::approx::*;
+
+    let txt = "@model_properties_format_version 0.2
+@orbital_energies unit=hartree
+-0.5 2.0
+-0.3 2.0
+0.1 0.0
+0.2 0.0
+@scf_converged
+true
+@scf_iterations
+12
+@end
+";
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.get_orbital_energies().map(|e| e.len()), Some(4));
+    assert_eq!(r.get_scf_converged(), Some(true));
+    assert_eq!(r.get_scf_iterations(), Some(12));
+    let gap = r.homo_lumo_gap().unwrap();
+    assert_relative_eq!(gap, 0.4 * 27.211386245988, epsilon = 1e-8);
+
+    let r_: Computed = r.to_string().parse()?;
+    assert_relative_eq!(r_.homo_lumo_gap().unwrap(), gap, epsilon = 1e-8);
+    assert_eq!(r_.get_scf_iterations(), Some(12));
+
+    // no occupations
+    let txt = "@model_properties_format_version 0.2\n@orbital_energies\n-0.5\n0.1\n@end\n";
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.homo_lumo_gap(), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_model_parse_meta() -> Result<()> {
+    let txt = "@model_properties_format_version 0.2
+@meta program=xtb
+version=6.5.1
+method = GFN2-xTB
+@energy
+-1.0
+@end
+";
+    let r: Computed = txt.parse()?;
+    assert_eq!(r.get_meta("program"), Some("xtb"));
+    assert_eq!(r.get_meta("version"), Some("6.5.1"));
+    assert_eq!(r.get_meta("method"), Some("GFN2-xTB"));
+
+    let r_: Computed = r.to_string().parse()?;
+    assert_eq!(r_.meta().count(), 3);
+    let r_: Computed = serde_json::from_str(&serde_json::to_string(&r)?)?;
+    assert_eq!(r_.get_meta("method"), Some("GFN2-xTB"));
+
+    Ok(())
+}
+
+#[test]
+fn test_model_validate_against() -> Result<()> {
+    let txt = gchemol::io::read_file("tests/files/sample.txt")?;
+    let r: Computed = txt.parse()?;
+    let mut mol = r.get_molecule().expect("structure").clone();
+    r.validate_against(&mol)?;
+
+    // element order
+    let mut mol_ = mol.clone();
+    let sns = mol_.serial_numbers().collect_vec();
+    mol_.get_atom_mut(sns[0]).unwrap().set_symbol("O");
+    assert!(r.validate_against(&mol_).is_err());
+
+    // lattice presence: structure without cell is accepted for periodic
+    // system, but not the reverse
+    let cell = Lattice::new([[10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]]);
+    mol.set_lattice(cell);
+    r.validate_against(&mol)?;
+    let mut r_ = r.clone();
+    r_.molecule = Some(mol.clone());
+    mol.unbuild_crystal();
+    assert!(r_.validate_against(&mol).is_err());
+
+    // wrong forces or invalid values
+    let mut r = Computed::default();
+    r.set_forces(vec![[0.0; 3]; 2]);
+    assert!(r.validate_against(&mol).is_err());
+    r.set_forces(vec![[0.0; 3]; 3]);
+    r.validate_against(&mol)?;
+    r.set_energy(f64::NAN);
+    assert!(r.validate_against(&mol).is_err());
+
+    Ok(())
 }
 
 #[test]
@@ -356,7 +2654,7 @@ fn test_model_parse_results_special() -> Result<()> {
 
     let txt = gchemol::io::read_file("./tests/files/sample_special.txt")?;
     let r: Computed = txt.parse()?;
-    assert_eq!(r.energy.unwrap(), 0.0);
+    assert_eq!(r.energy.unwrap(), -0.32933619218901E+00);
     assert_eq!(r.forces.unwrap()[0][0], 0.10525500903260E-03);
 
     Ok(())
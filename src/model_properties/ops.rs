@@ -0,0 +1,198 @@
+// [[file:../../models.note::*imports][imports:1]]
+use super::*;
+
+use std::ops::{Add, Mul, Sub};
+// imports:1 ends here
+
+// [[file:../../models.note::5e0b7c93][5e0b7c93]]
+// Return the number of atoms implied by computed properties, if any.
+fn implied_natoms(c: &Computed) -> Option<usize> {
+    c.molecule
+        .as_ref()
+        .map(|mol| mol.natoms())
+        .or_else(|| c.get_forces().map(|f| f.len()))
+        .or_else(|| c.hessian.as_ref().and_then(|h| packed_dimension(h.len()).ok()).map(|n| n / 3))
+}
+
+// Combine optional values in both operands, or drop if missing in any.
+fn zip_with<T, F>(a: &Option<T>, b: &Option<T>, f: F) -> Option<T>
+where
+    F: Fn(&T, &T) -> T,
+{
+    Some(f(a.as_ref()?, b.as_ref()?))
+}
+
+fn axpy_xyz(a: &[[f64; 3]], b: &[[f64; 3]], s: f64) -> Vec<[f64; 3]> {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| [x[0] + s * y[0], x[1] + s * y[1], x[2] + s * y[2]])
+        .collect()
+}
+
+fn axpy_tensor(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3], s: f64) -> [[f64; 3]; 3] {
+    let mut t = *a;
+    for (row, r) in t.iter_mut().zip(b) {
+        for (x, y) in row.iter_mut().zip(r) {
+            *x += s * y;
+        }
+    }
+    t
+}
+
+fn axpy_values(a: &[f64], b: &[f64], s: f64) -> Vec<f64> {
+    a.iter().zip(b).map(|(x, y)| x + s * y).collect()
+}
+
+impl Computed {
+    // Return `self + s * other` for additive properties.
+    fn axpy(&self, other: &Computed, s: f64) -> Result<Computed> {
+        if let (Some(na), Some(nb)) = (implied_natoms(self), implied_natoms(other)) {
+            ensure!(na == nb, "cannot combine computed results of {na} and {nb} atoms");
+        }
+        if let (Some(fa), Some(fb)) = (self.get_forces(), other.get_forces()) {
+            ensure!(fa.len() == fb.len(), "forces mismatch: {} vs {}", fa.len(), fb.len());
+        }
+        if let (Some(ha), Some(hb)) = (&self.hessian, &other.hessian) {
+            ensure!(ha.len() == hb.len(), "hessian mismatch: {} vs {}", ha.len(), hb.len());
+        }
+
+        let computed = Computed {
+            energy: zip_with(&self.get_energy(), &other.get_energy(), |a, b| a + s * b),
+            forces: zip_with(&self.get_forces().cloned(), &other.get_forces().cloned(), |a, b| {
+                axpy_xyz(a, b, s)
+            }),
+            dipole: zip_with(&self.dipole, &other.dipole, |a, b| [0, 1, 2].map(|i| a[i] + s * b[i])),
+            stress: zip_with(&self.stress, &other.stress, |a, b| axpy_tensor(a, b, s)),
+            virial: zip_with(&self.virial, &other.virial, |a, b| axpy_tensor(a, b, s)),
+            hessian: zip_with(&self.hessian, &other.hessian, |a, b| axpy_values(a, b, s)),
+            molecule: self.molecule.clone().or_else(|| other.molecule.clone()),
+            ..Default::default()
+        };
+
+        Ok(computed)
+    }
+
+    /// Add up energy, forces, dipole, stress, virial and Hessian of `self`
+    /// and `other` component-wise, such as adding a dispersion correction.
+    /// Energy and forces are taken from the active state if set.
+    /// Properties missing in either operand are dropped, and the structure
+    /// is taken from `self` if available, otherwise from `other`.
+    ///
+    /// Return error if the number of atoms disagrees.
+    pub fn checked_add(&self, other: &Computed) -> Result<Computed> {
+        self.axpy(other, 1.0)
+    }
+
+    /// Subtract `other` from `self` component-wise, such as for Δ-learning.
+    /// See also [`Computed::checked_add`].
+    pub fn checked_sub(&self, other: &Computed) -> Result<Computed> {
+        self.axpy(other, -1.0)
+    }
+
+    /// Scale energy, forces, dipole, stress, virial and Hessian by `s`. The
+    /// structure is carried through, while other properties are dropped.
+    pub fn scaled(&self, s: f64) -> Computed {
+        let scale = |t: &[[f64; 3]; 3]| t.map(|r| r.map(|x| x * s));
+        Computed {
+            energy: self.get_energy().map(|e| e * s),
+            forces: self.get_forces().map(|f| f.iter().map(|v| v.map(|x| x * s)).collect()),
+            dipole: self.dipole.map(|d| d.map(|x| x * s)),
+            stress: self.stress.as_ref().map(scale),
+            virial: self.virial.as_ref().map(scale),
+            hessian: self.hessian.as_ref().map(|h| h.iter().map(|x| x * s).collect()),
+            molecule: self.molecule.clone(),
+            ..Default::default()
+        }
+    }
+}
+// 5e0b7c93 ends here
+
+// [[file:../../models.note::c1a7d4e8][c1a7d4e8]]
+/// Panics if the number of atoms disagrees. Use `checked_add` instead for
+/// error handling.
+impl Add for &Computed {
+    type Output = Computed;
+
+    fn add(self, other: &Computed) -> Computed {
+        self.checked_add(other).expect("add computed results")
+    }
+}
+
+impl Add for Computed {
+    type Output = Computed;
+
+    fn add(self, other: Computed) -> Computed {
+        &self + &other
+    }
+}
+
+/// Panics if the number of atoms disagrees. Use `checked_sub` instead for
+/// error handling.
+impl Sub for &Computed {
+    type Output = Computed;
+
+    fn sub(self, other: &Computed) -> Computed {
+        self.checked_sub(other).expect("subtract computed results")
+    }
+}
+
+impl Sub for Computed {
+    type Output = Computed;
+
+    fn sub(self, other: Computed) -> Computed {
+        &self - &other
+    }
+}
+
+impl Mul<f64> for &Computed {
+    type Output = Computed;
+
+    fn mul(self, s: f64) -> Computed {
+        self.scaled(s)
+    }
+}
+
+impl Mul<f64> for Computed {
+    type Output = Computed;
+
+    fn mul(self, s: f64) -> Computed {
+        self.scaled(s)
+    }
+}
+// c1a7d4e8 ends here
+
+// [[file:../../models.note::0f3e9b26][0f3e9b26]]
+#[test]
+fn test_computed_ops() -> Result<()> {
+    let mut reference = Computed::default();
+    reference.set_energy(-10.0);
+    reference.set_forces(vec![[0.5, 0.25, 1.0], [-0.5, -0.25, -1.0]]);
+    reference.set_dipole([1.0, 0.0, 0.0]);
+    reference.set_stress([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+
+    let mut cheap = Computed::default();
+    cheap.set_energy(-9.0);
+    cheap.set_forces(vec![[0.0, 0.25, 0.5], [0.0, -0.25, -0.5]]);
+    cheap.set_stress([[0.5, 0.0, 0.0], [0.0, 0.5, 0.0], [0.0, 0.0, 0.5]]);
+
+    let delta = reference.checked_sub(&cheap)?;
+    assert_eq!(delta.get_energy(), Some(-1.0));
+    assert_eq!(delta.get_forces().unwrap()[1], [-0.5, 0.0, -0.5]);
+    assert_eq!(delta.get_stress().unwrap()[2][2], 0.5);
+    // missing in `cheap`
+    assert_eq!(delta.get_dipole(), None);
+
+    let total = &cheap + &delta;
+    assert_eq!(total.get_energy(), reference.get_energy());
+    let scaled = reference * 0.5;
+    assert_eq!(scaled.get_energy(), Some(-5.0));
+    assert_eq!(scaled.get_dipole(), Some([0.5, 0.0, 0.0]));
+    assert_eq!(scaled.get_forces().unwrap()[0], [0.25, 0.125, 0.5]);
+
+    // mismatched atoms
+    cheap.set_forces(vec![[0.0; 3]]);
+    assert!(delta.checked_add(&cheap).is_err());
+
+    Ok(())
+}
+// 0f3e9b26 ends here
@@ -0,0 +1,151 @@
+// [[file:../models.note::*header][header:1]]
+//! Physical units for converting model properties into canonical units
+//!
+//! The canonical units are eV for energy, Å for length, and elementary charge
+//! (e) for charge. Named units can be combined as `energy/length^n`, such as
+//! `hartree/bohr` or `kcal/mol/angstrom^2`.
+// header:1 ends here
+
+// [[file:../models.note::3f0b9a61][3f0b9a61]]
+use super::*;
+
+/// Hartree energy in eV
+const HARTREE: f64 = 27.211386245988;
+/// Bohr radius in Å
+const BOHR: f64 = 0.529177210903;
+/// kcal/mol in eV
+const KCAL_MOL: f64 = 0.0433641043;
+/// kJ/mol in eV
+const KJ_MOL: f64 = 0.0103642697;
+/// GPa in eV/Å^3
+const GPA: f64 = 1.0 / 160.21766208;
+/// Debye in e·Å
+const DEBYE: f64 = 0.2081943;
+
+const ENERGY_UNITS: [(&str, f64); 9] = [
+    ("ev", 1.0),
+    ("mev", 1e-3),
+    ("hartree", HARTREE),
+    ("ha", HARTREE),
+    ("rydberg", HARTREE / 2.0),
+    ("ry", HARTREE / 2.0),
+    ("kcal/mol", KCAL_MOL),
+    ("kj/mol", KJ_MOL),
+    ("au", HARTREE),
+];
+
+const LENGTH_UNITS: [(&str, f64); 6] = [
+    ("angstrom", 1.0),
+    ("ang", 1.0),
+    ("a", 1.0),
+    ("bohr", BOHR),
+    ("nm", 10.0),
+    ("pm", 0.01),
+];
+// 3f0b9a61 ends here
+
+// [[file:../models.note::8e4c2d17][8e4c2d17]]
+/// Physical dimension of model properties
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dimension {
+    /// Energy, in eV
+    Energy,
+    /// Length, in Å
+    Length,
+    /// Energy/length, in eV/Å
+    Force,
+    /// Energy/length^2, in eV/Å^2
+    Hessian,
+    /// Energy/length^3, in eV/Å^3
+    Stress,
+    /// Length^3, in Å^3
+    Volume,
+    /// Charge times length, in e·Å
+    Dipole,
+    /// Charge, in e
+    Charge,
+}
+
+// Split named energy unit from the leading part of `unit`, returning its
+// factor and the remaining part.
+fn split_energy_unit(unit: &str) -> Option<(f64, &str)> {
+    ENERGY_UNITS.iter().find_map(|&(name, f)| {
+        let rest = unit.strip_prefix(name)?;
+        (rest.is_empty() || rest.starts_with('/')).then_some((f, rest))
+    })
+}
+
+// Parse length unit with optional power, such as `bohr^2`.
+fn parse_length_unit(unit: &str) -> Option<(f64, i32)> {
+    let (name, power) = match unit.split_once('^') {
+        Some((name, p)) => (name, p.parse().ok()?),
+        None => (unit, 1),
+    };
+    let f = LENGTH_UNITS.iter().find(|&&(x, _)| x == name)?.1;
+    Some((f, power))
+}
+
+// Convert `energy/length^n` unit
+fn energy_per_length(unit: &str, n: i32) -> Option<f64> {
+    let (fe, rest) = split_energy_unit(unit)?;
+    let (fl, power) = parse_length_unit(rest.strip_prefix('/')?)?;
+    (power == n).then(|| fe / fl.powi(n))
+}
+
+/// Return the factor for converting values in `unit` into canonical units of
+/// `dim`.
+pub(crate) fn conversion_factor(dim: Dimension, unit: &str) -> Result<f64> {
+    use Dimension::*;
+
+    let unit = unit.to_lowercase();
+    let f = match (dim, unit.as_str()) {
+        (Force, "au") => Some(HARTREE / BOHR),
+        (Hessian, "au") => Some(HARTREE / BOHR.powi(2)),
+        (Stress, "au") => Some(HARTREE / BOHR.powi(3)),
+        (Stress, "gpa") => Some(GPA),
+        (Stress, "kbar") => Some(0.1 * GPA),
+        (Stress, "bar") => Some(1e-4 * GPA),
+        (Volume, "au") => Some(BOHR.powi(3)),
+        (Dipole, "debye") => Some(DEBYE),
+        (Dipole, "au") | (Dipole, "e*bohr") => Some(BOHR),
+        (Dipole, "e*angstrom") => Some(1.0),
+        (Charge, "e") | (Charge, "au") => Some(1.0),
+        (Length, "au") => Some(BOHR),
+        (Energy, u) => split_energy_unit(u).and_then(|(f, rest)| rest.is_empty().then_some(f)),
+        (Length, u) => parse_length_unit(u).and_then(|(f, n)| (n == 1).then_some(f)),
+        (Volume, u) => parse_length_unit(u).and_then(|(f, n)| (n == 3).then(|| f.powi(3))),
+        (Force, u) => energy_per_length(u, 1),
+        (Hessian, u) => energy_per_length(u, 2),
+        (Stress, u) => energy_per_length(u, 3),
+        _ => None,
+    };
+
+    f.ok_or_else(|| format_err!("unknown unit {unit:?} for {dim:?}"))
+}
+// 8e4c2d17 ends here
+
+// [[file:../models.note::5a1f6e0c][5a1f6e0c]]
+#[test]
+fn test_units() -> Result<()> {
+    use vecfx::approx::*;
+    use Dimension::*;
+
+    assert_eq!(conversion_factor(Energy, "eV")?, 1.0);
+    assert_eq!(conversion_factor(Energy, "Hartree")?, HARTREE);
+    assert_eq!(conversion_factor(Energy, "kcal/mol")?, KCAL_MOL);
+    assert_relative_eq!(conversion_factor(Force, "hartree/bohr")?, 51.42206747632589, epsilon = 1e-8);
+    assert_eq!(conversion_factor(Force, "au")?, conversion_factor(Force, "hartree/bohr")?);
+    assert_eq!(conversion_factor(Force, "kcal/mol/angstrom")?, KCAL_MOL);
+    assert_eq!(conversion_factor(Hessian, "hartree/bohr^2")?, HARTREE / BOHR / BOHR);
+    assert_relative_eq!(conversion_factor(Stress, "GPa")?, 0.0062415091, epsilon = 1e-8);
+    assert_eq!(conversion_factor(Volume, "bohr^3")?, BOHR.powi(3));
+
+    // unknown or mismatched units
+    assert!(conversion_factor(Energy, "hartree/bohr").is_err());
+    assert!(conversion_factor(Force, "hartree").is_err());
+    assert!(conversion_factor(Force, "hartree/bohr^2").is_err());
+    assert!(conversion_factor(Energy, "furlong").is_err());
+
+    Ok(())
+}
+// 5a1f6e0c ends here
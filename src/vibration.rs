@@ -0,0 +1,392 @@
+// [[file:../models.note::*header][header:1]]
+//! Harmonic vibrational analysis from Hessian matrix
+// header:1 ends here
+
+// [[file:../models.note::9c4e1b07][9c4e1b07]]
+use super::*;
+
+use gchemol::Molecule;
+use vecfx::nalgebra::{DMatrix, DVector, Matrix3};
+
+/// Boltzmann constant in eV/K
+const KB: f64 = 8.617333262e-5;
+/// Planck constant in eV·s
+const PLANCK: f64 = 4.135667696e-15;
+/// Electron volt in J
+const EV: f64 = 1.602176634e-19;
+/// Atomic mass unit in kg
+const AMU: f64 = 1.66053906660e-27;
+/// Speed of light in cm/s
+const LIGHT_SPEED: f64 = 2.99792458e10;
+
+// Angular frequency in rad/s for eigenvalue 1 eV/(Å^2·amu) of mass-weighted
+// Hessian
+fn angular_frequency_unit() -> f64 {
+    (EV / (AMU * 1e-20)).sqrt()
+}
+// 9c4e1b07 ends here
+
+// [[file:../models.note::2b8f5d60][2b8f5d60]]
+/// Harmonic vibrational analysis of a molecule or a periodic system
+#[derive(Debug, Clone)]
+pub struct VibrationalAnalysis {
+    /// Vibrational frequencies in cm^-1. Imaginary frequencies are
+    /// represented as negative values.
+    frequencies: Vec<f64>,
+    /// Normalized Cartesian displacements of normal modes
+    modes: Vec<Vec<[f64; 3]>>,
+    /// Total mass in amu
+    mass: f64,
+    /// Principal moments of inertia in amu·Å^2, for molecule only
+    inertia: Option<[f64; 3]>,
+    /// Linear molecule or not
+    linear: bool,
+    /// Rotational symmetry number
+    symmetry_number: usize,
+}
+
+/// Harmonic thermochemistry at given temperature and pressure. All energies
+/// are corrections to be added to the electronic energy.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Thermochemistry {
+    /// Temperature in K
+    pub temperature: f64,
+    /// Pressure in Pa
+    pub pressure: f64,
+    /// Zero-point energy in eV
+    pub zero_point_energy: f64,
+    /// Enthalpy correction in eV, including zero-point energy
+    pub enthalpy: f64,
+    /// Entropy in eV/K
+    pub entropy: f64,
+    /// Gibbs free energy correction in eV (H - TS)
+    pub free_energy: f64,
+}
+
+// Return orthonormal vectors in mass-weighted coordinates for translations,
+// and also rotations for non-periodic `mol`.
+fn external_motions(mol: &Molecule, masses: &[f64]) -> Vec<DVector<f64>> {
+    let n = 3 * masses.len();
+    let mut vectors = vec![];
+    for k in 0..3 {
+        let mut v = DVector::zeros(n);
+        for (i, m) in masses.iter().enumerate() {
+            v[3 * i + k] = m.sqrt();
+        }
+        vectors.push(v);
+    }
+    if mol.get_lattice().is_none() {
+        let com = center_of_mass(mol, masses);
+        for k in 0..3 {
+            let mut v = DVector::zeros(n);
+            for (i, (p, m)) in mol.positions().zip(masses).enumerate() {
+                let r = [p[0] - com[0], p[1] - com[1], p[2] - com[2]];
+                // e_k x r
+                let (a, b) = ((k + 1) % 3, (k + 2) % 3);
+                v[3 * i + b] = r[a] * m.sqrt();
+                v[3 * i + a] = -r[b] * m.sqrt();
+            }
+            vectors.push(v);
+        }
+    }
+
+    // Gram-Schmidt orthonormalization, dropping dependent vectors such as
+    // rotations around the axis of linear molecule
+    let mut basis: Vec<DVector<f64>> = vec![];
+    for mut v in vectors {
+        for u in &basis {
+            v -= u * u.dot(&v);
+        }
+        let norm = v.norm();
+        if norm > 1e-6 {
+            basis.push(v / norm);
+        }
+    }
+    basis
+}
+
+fn center_of_mass(mol: &Molecule, masses: &[f64]) -> [f64; 3] {
+    let total: f64 = masses.iter().sum();
+    let mut com = [0.0; 3];
+    for (p, m) in mol.positions().zip(masses) {
+        for k in 0..3 {
+            com[k] += m * p[k] / total;
+        }
+    }
+    com
+}
+
+// Principal moments of inertia in ascending order
+fn principal_moments(mol: &Molecule, masses: &[f64]) -> [f64; 3] {
+    let com = center_of_mass(mol, masses);
+    let mut t = Matrix3::<f64>::zeros();
+    for (p, m) in mol.positions().zip(masses) {
+        let r = [p[0] - com[0], p[1] - com[1], p[2] - com[2]];
+        let r2 = r[0] * r[0] + r[1] * r[1] + r[2] * r[2];
+        for a in 0..3 {
+            for b in 0..3 {
+                let delta = if a == b { r2 } else { 0.0 };
+                t[(a, b)] += m * (delta - r[a] * r[b]);
+            }
+        }
+    }
+    let mut moments = t.symmetric_eigenvalues().as_slice().to_vec();
+    moments.sort_by(|a, b| a.total_cmp(b));
+    [moments[0], moments[1], moments[2]]
+}
+
+impl VibrationalAnalysis {
+    /// Perform harmonic vibrational analysis for `mol` using the full 3Nx3N
+    /// `hessian` in eV/Å^2. The Hessian is mass-weighted using element
+    /// masses in `mol`, and the translations (and rotations for non-periodic
+    /// system) are projected out.
+    pub fn new(mol: &Molecule, hessian: &[Vec<f64>]) -> Result<Self> {
+        let natoms = mol.natoms();
+        let n = 3 * natoms;
+        ensure!(natoms > 0, "no atoms in molecule");
+        ensure!(
+            hessian.len() == n && hessian.iter().all(|row| row.len() == n),
+            "expect {n}x{n} hessian for {natoms} atoms"
+        );
+        let masses = mol.masses().collect_vec();
+        if let Some(i) = masses.iter().position(|&m| m <= 0.0) {
+            bail!("invalid mass of atom {}: {}", i + 1, masses[i]);
+        }
+
+        // mass-weighted hessian
+        let h = DMatrix::from_fn(n, n, |i, j| hessian[i][j] / (masses[i / 3] * masses[j / 3]).sqrt());
+        // project out external motions
+        let external = external_motions(mol, &masses);
+        let mut p = DMatrix::identity(n, n);
+        for v in &external {
+            p -= v * v.transpose();
+        }
+        let h = &p * h * &p;
+        let h = (&h + h.transpose()) * 0.5;
+
+        // drop the modes mostly in the space of external motions
+        let eigen = h.symmetric_eigen();
+        let mut internal = (0..n)
+            .map(|i| {
+                let v = eigen.eigenvectors.column(i);
+                let overlap: f64 = external.iter().map(|u| u.dot(&v).powi(2)).sum();
+                (i, overlap)
+            })
+            .collect_vec();
+        internal.sort_by(|a, b| a.1.total_cmp(&b.1));
+        internal.truncate(n.saturating_sub(external.len()));
+        internal.sort_by(|a, b| eigen.eigenvalues[a.0].total_cmp(&eigen.eigenvalues[b.0]));
+
+        let unit = angular_frequency_unit() / (2.0 * std::f64::consts::PI * LIGHT_SPEED);
+        let mut frequencies = vec![];
+        let mut modes = vec![];
+        for (i, _) in internal {
+            let lambda = eigen.eigenvalues[i];
+            frequencies.push(lambda.signum() * lambda.abs().sqrt() * unit);
+            // convert into Cartesian displacements
+            let v = eigen.eigenvectors.column(i);
+            let d = (0..n).map(|k| v[k] / masses[k / 3].sqrt()).collect_vec();
+            let norm = d.iter().map(|x| x * x).sum::<f64>().sqrt();
+            modes.push(d.chunks(3).map(|x| [x[0] / norm, x[1] / norm, x[2] / norm]).collect());
+        }
+
+        let periodic = mol.get_lattice().is_some();
+        let vib = Self {
+            frequencies,
+            modes,
+            mass: masses.iter().sum(),
+            inertia: (!periodic).then(|| principal_moments(mol, &masses)),
+            linear: !periodic && external.len() == 5,
+            symmetry_number: 1,
+        };
+        Ok(vib)
+    }
+
+    /// Set rotational symmetry number for thermochemistry, default is 1.
+    pub fn set_symmetry_number(&mut self, sigma: usize) {
+        self.symmetry_number = sigma.max(1);
+    }
+
+    /// Return vibrational frequencies in cm^-1 in ascending order. Imaginary
+    /// frequencies are returned as negative values.
+    pub fn frequencies(&self) -> &[f64] {
+        &self.frequencies
+    }
+
+    /// Return normalized Cartesian displacements of normal modes, in the
+    /// same order as frequencies.
+    pub fn normal_modes(&self) -> &[Vec<[f64; 3]>] {
+        &self.modes
+    }
+
+    /// Return true if the molecule is linear.
+    pub fn is_linear(&self) -> bool {
+        self.linear
+    }
+
+    // Return vibrational energies in eV of real frequencies.
+    fn vibrational_energies(&self) -> impl Iterator<Item = f64> + '_ {
+        let unit = PLANCK * LIGHT_SPEED;
+        self.frequencies.iter().filter(|&&f| f > 0.0).map(move |f| f * unit)
+    }
+
+    /// Return zero-point energy in eV. Imaginary frequencies are ignored.
+    pub fn zero_point_energy(&self) -> f64 {
+        self.vibrational_energies().map(|e| 0.5 * e).sum()
+    }
+
+    /// Return harmonic thermochemistry at `temperature` in K and `pressure`
+    /// in Pa. Translations and rotations are treated as ideal gas for
+    /// molecule, and only vibrations are considered for periodic system.
+    /// Imaginary frequencies are ignored.
+    pub fn thermochemistry(&self, temperature: f64, pressure: f64) -> Result<Thermochemistry> {
+        ensure!(temperature > 0.0, "invalid temperature: {temperature}");
+        ensure!(pressure > 0.0, "invalid pressure: {pressure}");
+        let nimag = self.frequencies.iter().filter(|&&f| f < 0.0).count();
+        if nimag > 0 {
+            warn!("ignored {nimag} imaginary frequencies in thermochemistry");
+        }
+
+        let kt = KB * temperature;
+        let zpe = self.zero_point_energy();
+        let mut enthalpy = zpe;
+        let mut entropy = 0.0;
+        // vibrations
+        for e in self.vibrational_energies() {
+            let x = e / kt;
+            enthalpy += e / x.exp_m1();
+            entropy += KB * (x / x.exp_m1() - (-(-x).exp()).ln_1p());
+        }
+
+        // translations and rotations of molecule
+        if let Some(inertia) = self.inertia {
+            // in SI units
+            let kt_si = kt * EV;
+            let h_si = PLANCK * EV;
+            let m = self.mass * AMU;
+            let q_trans = (2.0 * std::f64::consts::PI * m * kt_si / h_si.powi(2)).powf(1.5) * kt_si / pressure;
+            entropy += KB * (q_trans.ln() + 2.5);
+            // including PV = kT
+            enthalpy += 2.5 * kt;
+
+            let sigma = self.symmetry_number as f64;
+            let pi = std::f64::consts::PI;
+            let inertia = inertia.map(|x| x * AMU * 1e-20);
+            if self.linear {
+                let q_rot = 8.0 * pi.powi(2) * inertia[2] * kt_si / (sigma * h_si.powi(2));
+                entropy += KB * (q_rot.ln() + 1.0);
+                enthalpy += kt;
+            } else if self.mass > 0.0 && inertia[0] > 0.0 {
+                let q_rot = (pi * inertia[0] * inertia[1] * inertia[2]).sqrt() / sigma
+                    * (8.0 * pi.powi(2) * kt_si / h_si.powi(2)).powf(1.5);
+                entropy += KB * (q_rot.ln() + 1.5);
+                enthalpy += 1.5 * kt;
+            }
+        }
+
+        let thermo = Thermochemistry {
+            temperature,
+            pressure,
+            zero_point_energy: zpe,
+            enthalpy,
+            entropy,
+            free_energy: enthalpy - temperature * entropy,
+        };
+        Ok(thermo)
+    }
+}
+
+impl Computed {
+    /// Perform harmonic vibrational analysis for `mol` using computed Hessian
+    /// (force constants). See also [`VibrationalAnalysis::new`].
+    pub fn vibrational_analysis(&self, mol: &Molecule) -> Result<VibrationalAnalysis> {
+        let hessian = self.get_hessian().ok_or_else(|| format_err!("no hessian available"))?;
+        VibrationalAnalysis::new(mol, &hessian)
+    }
+}
+// 2b8f5d60 ends here
+
+// [[file:../models.note::7e3a9f14][7e3a9f14]]
+#[test]
+fn test_vibrational_analysis() -> Result<()> {
+    use vecfx::approx::*;
+
+    // a diatomic molecule with harmonic bond along x axis
+    let mol = Molecule::from_atoms(vec![("H", [0.0, 0.0, 0.0]), ("H", [0.74, 0.0, 0.0])]);
+    let k = 36.0;
+    let mut hessian = vec![vec![0.0; 6]; 6];
+    hessian[0][0] = k;
+    hessian[3][3] = k;
+    hessian[0][3] = -k;
+    hessian[3][0] = -k;
+
+    let mut computed = Computed::default();
+    computed.set_hessian(hessian)?;
+    let mut vib = computed.vibrational_analysis(&mol)?;
+    vib.set_symmetry_number(2);
+    assert!(vib.is_linear());
+    assert_eq!(vib.frequencies().len(), 1);
+    let mass = mol.masses().next().unwrap();
+    let unit = angular_frequency_unit() / (2.0 * std::f64::consts::PI * LIGHT_SPEED);
+    let expected = (k / (0.5 * mass)).sqrt() * unit;
+    assert_relative_eq!(vib.frequencies()[0], expected, epsilon = 1e-6);
+    let mode = &vib.normal_modes()[0];
+    assert_relative_eq!(mode[0][0], -mode[1][0], epsilon = 1e-8);
+
+    let zpe = vib.zero_point_energy();
+    assert_relative_eq!(zpe, 0.5 * expected * PLANCK * LIGHT_SPEED, epsilon = 1e-8);
+    // reference values from ideal gas, rigid rotor and harmonic oscillator
+    // model, evaluated in SI units
+    let thermo = vib.thermochemistry(298.15, 101325.0)?;
+    assert_relative_eq!(thermo.zero_point_energy, 0.27321365546, max_relative = 1e-6);
+    assert_relative_eq!(thermo.enthalpy, 0.36313768270, max_relative = 1e-6);
+    assert_relative_eq!(thermo.entropy, 1.3494104443e-3, max_relative = 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn test_vibrational_analysis_nonlinear() -> Result<()> {
+    use vecfx::approx::*;
+
+    // an equilateral triangle of H atoms connected by harmonic springs (D3h)
+    let a = 0.9;
+    let h = a * 3f64.sqrt() / 2.0;
+    let atoms = vec![("H", [0.0, 0.0, 0.0]), ("H", [a, 0.0, 0.0]), ("H", [a / 2.0, h, 0.0])];
+    let mol = Molecule::from_atoms(atoms);
+    let k = 30.0;
+    let positions: Vec<_> = mol.positions().collect();
+    let mut hessian = vec![vec![0.0; 9]; 9];
+    for (i, j) in [(0, 1), (0, 2), (1, 2)] {
+        let u: Vec<_> = (0..3).map(|x| (positions[j][x] - positions[i][x]) / a).collect();
+        for x in 0..3 {
+            for y in 0..3 {
+                let kxy = k * u[x] * u[y];
+                hessian[3 * i + x][3 * i + y] += kxy;
+                hessian[3 * j + x][3 * j + y] += kxy;
+                hessian[3 * i + x][3 * j + y] -= kxy;
+                hessian[3 * j + x][3 * i + y] -= kxy;
+            }
+        }
+    }
+
+    let mut computed = Computed::default();
+    computed.set_hessian(hessian)?;
+    let mut vib = computed.vibrational_analysis(&mol)?;
+    vib.set_symmetry_number(6);
+    assert!(!vib.is_linear());
+    // two degenerate modes and one breathing mode
+    let freqs = vib.frequencies();
+    assert_eq!(freqs.len(), 3);
+    assert_relative_eq!(freqs[0], 3484.2239980, max_relative = 1e-6);
+    assert_relative_eq!(freqs[1], 3484.2239980, max_relative = 1e-6);
+    assert_relative_eq!(freqs[2], 4927.4368323, max_relative = 1e-6);
+
+    let thermo = vib.thermochemistry(298.15, 101325.0)?;
+    assert_relative_eq!(thermo.zero_point_energy, 0.73745087255, max_relative = 1e-6);
+    assert_relative_eq!(thermo.enthalpy, 0.84022123215, max_relative = 1e-6);
+    assert_relative_eq!(thermo.entropy, 1.5326636218e-3, max_relative = 1e-6);
+
+    Ok(())
+}
+// 7e3a9f14 ends here
@@ -0,0 +1,9 @@
+#! /usr/bin/env bash
+
+# A dummy model for test purpose: crash after writing out an incomplete entry
+# of model properties.
+cat > /dev/null
+echo "@model_properties_format_version 0.2"
+echo "@energy"
+echo "SCF crashed" >&2
+exit 3
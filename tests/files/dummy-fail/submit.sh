@@ -0,0 +1,10 @@
+#! /usr/bin/env bash
+
+# A dummy model for test purpose: consume stdin, write lots of messages to
+# stderr, and exit with failure.
+cat > /dev/null
+for i in $(seq 1 100); do
+    echo "line $i" >&2
+done
+echo "SCF crashed" >&2
+exit 3
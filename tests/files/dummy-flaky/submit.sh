@@ -0,0 +1,11 @@
+#! /usr/bin/env bash
+
+# A dummy model for test purpose: fail on the first call in the scratch
+# directory, and then behave like dummy-sp.
+if [[ ! -e failed ]]; then
+    touch failed
+    cat > /dev/null
+    echo "licence checkout failed" >&2
+    exit 1
+fi
+exec "$BBM_TPL_DIR/../dummy-sp/submit.sh"
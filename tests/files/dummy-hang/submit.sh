@@ -0,0 +1,10 @@
+#! /usr/bin/env bash
+
+# A dummy model for test purpose: hang on the first call in the scratch
+# directory, and then behave like dummy-sp.
+if [[ ! -e hung ]]; then
+    touch hung
+    # a grandchild process holding stdout
+    sleep 60
+fi
+exec "$BBM_TPL_DIR/../dummy-sp/submit.sh"
@@ -0,0 +1,24 @@
+#! /usr/bin/env bash
+
+# A dummy model for test purpose: same as dummy-sp, but reports that SCF
+# iterations did not converge.
+natoms=0
+while read -r line; do
+    if [[ "$line" == "END" ]]; then
+        echo "@model_properties_format_version 0.2"
+        echo "@energy"
+        echo "-$natoms"
+        echo "@forces"
+        for ((i = 0; i < natoms; i++)); do
+            echo "0.0 0.0 0.0"
+        done
+        echo "@scf_converged"
+        echo "false"
+        echo "@scf_iterations"
+        echo "100"
+        echo "@end"
+        natoms=0
+    elif [[ -n "$line" ]]; then
+        natoms=$((natoms + 1))
+    fi
+done
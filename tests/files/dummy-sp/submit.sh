@@ -0,0 +1,21 @@
+#! /usr/bin/env bash
+
+# A dummy model for test purpose: for each structure read from stdin, which is
+# terminated by a line of "END", write out an entry of model properties with
+# energy of minus the number of atoms and zero forces.
+natoms=0
+while read -r line; do
+    if [[ "$line" == "END" ]]; then
+        echo "@model_properties_format_version 0.2"
+        echo "@energy"
+        echo "-$natoms"
+        echo "@forces"
+        for ((i = 0; i < natoms; i++)); do
+            echo "0.0 0.0 0.0"
+        done
+        echo "@end"
+        natoms=0
+    elif [[ -n "$line" ]]; then
+        natoms=$((natoms + 1))
+    fi
+done
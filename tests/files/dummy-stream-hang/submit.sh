@@ -0,0 +1,13 @@
+#! /usr/bin/env bash
+
+# A dummy model for test purpose: write out an entry of model properties, and
+# then hang with a grandchild process holding stdout. The PID is recorded in
+# the scratch directory.
+echo $$ > pid
+cat > /dev/null
+sleep 60 &
+echo "@model_properties_format_version 0.2"
+echo "@energy"
+echo "-1.0"
+echo "@end"
+wait